@@ -1,10 +1,15 @@
-use log::info;
-use skyfeed::{Feed, FeedHandler, FeedResult, Post, Request, Uri};
+use skyfeed::{Feed, FeedError, FeedHandler, FeedResult, Post, Request, Uri};
 use std::{collections::HashSet, sync::Arc};
 use tokio::sync::Mutex;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+        .init();
+
     let mut feed = MyFeed {
         handler: MyFeedHandler {
             posts: Arc::new(Mutex::new(Vec::new())),
@@ -79,16 +84,17 @@ impl FeedHandler for MyFeedHandler {
         }
     }
 
-    async fn serve_feed(&self, request: Request) -> FeedResult {
+    async fn serve_feed(&self, request: Request) -> Result<FeedResult, FeedError> {
         info!("Serving {request:?}");
 
         let posts = self.posts.lock().await;
 
         // Parse the cursor from the request
-        let start_index = if let Some(cursor) = &request.cursor {
-            cursor.parse::<usize>().unwrap_or(0)
-        } else {
-            0
+        let start_index = match &request.cursor {
+            Some(cursor) => cursor
+                .parse::<usize>()
+                .map_err(|_| FeedError::InvalidCursor)?,
+            None => 0,
         };
 
         let posts_per_page = 5;
@@ -112,12 +118,12 @@ impl FeedHandler for MyFeedHandler {
             None
         };
 
-        FeedResult {
+        Ok(FeedResult {
             cursor: next_cursor,
             feed: page_posts
                 .into_iter()
-                .map(|post_with_likes| post_with_likes.post.uri.clone())
+                .map(|post_with_likes| post_with_likes.post.uri.clone().into())
                 .collect(),
-        }
+        })
     }
 }
@@ -1,144 +1,152 @@
-use log::info;
-use rusqlite::{params, Connection};
-use skyfeed::{Feed, FeedHandler, FeedResult, Post, Request, Uri};
-use std::{sync::Arc, time::Duration};
-use tokio::sync::Mutex;
+use skyfeed::{
+    And, Feed, FeedError, FeedHandler, FeedResult, FilteredHandler, Keywords, Not, Post,
+    PostFilter, Request, SkeletonItem, SqliteStore, Unlabeled, Uri,
+};
+use std::time::Duration;
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() {
-    let db = Connection::open("feed.db").expect("Failed to open database");
-    initialize_db(&db);
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+        .init();
 
-    let db = Arc::new(Mutex::new(db));
+    let store = SqliteStore::open("feed.db").expect("Failed to open database");
 
     let mut feed = MyFeed {
-        handler: MyFeedHandler { db: db.clone() },
+        handler: FilteredHandler::new(
+            cat_filter(),
+            MyFeedHandler {
+                store: store.clone(),
+            },
+        ),
     };
 
     let mut cleanup_interval = tokio::time::interval(Duration::from_secs(10));
     let cleanup_task = tokio::spawn(async move {
         loop {
             cleanup_interval.tick().await;
-            cleanup_posts(&db).await;
+            match store.prune_to_count(10_000).await {
+                Ok(cleaned_posts) => info!("Cleaned up {cleaned_posts} posts"),
+                Err(e) => error!("Failed to clean up old posts: {e}"),
+            }
         }
     });
 
-    tokio::join!(feed.start("Cats", ([0, 0, 0, 0], 3030)), cleanup_task)
-        .1
-        .expect("Starting tasks failed");
+    feed.start("Cats", ([0, 0, 0, 0], 3030)).await;
+    cleanup_task.abort();
+}
+
+type CatFilter = And<And<Keywords, Not<Keywords>>, Unlabeled>;
+
+/// Cat posts without politics or labels
+fn cat_filter() -> CatFilter {
+    Keywords::new([
+        "cat",
+        "cats",
+        "kitty",
+        "kitties",
+        "kitten",
+        "kittens",
+        "feline",
+        "catsofbluesky",
+    ])
+    .and(
+        Keywords::new([
+            "trump",
+            "kamala",
+            "harris",
+            "biden",
+            "democrat",
+            "democrats",
+            "democratic",
+            "republican",
+            "republicans",
+            "politics",
+            "dem",
+            "dems",
+            "aoc",
+            "gop",
+            "vance",
+            "musk",
+            "elon",
+            "walz",
+            "fascist",
+            "fascists",
+            "furryart",
+            "smut",
+            "furries",
+            "dnc",
+            "rfk",
+            "right wing",
+            "left wing",
+        ])
+        .not(),
+    )
+    .and(Unlabeled)
 }
 
 struct MyFeed {
-    handler: MyFeedHandler,
+    handler: FilteredHandler<CatFilter, MyFeedHandler>,
 }
 
-impl Feed<MyFeedHandler> for MyFeed {
-    fn handler(&mut self) -> MyFeedHandler {
+impl Feed<FilteredHandler<CatFilter, MyFeedHandler>> for MyFeed {
+    fn handler(&mut self) -> FilteredHandler<CatFilter, MyFeedHandler> {
         self.handler.clone()
     }
 }
 
 #[derive(Clone)]
 struct MyFeedHandler {
-    db: Arc<Mutex<Connection>>,
+    store: SqliteStore,
 }
 
 impl FeedHandler for MyFeedHandler {
     async fn insert_post(&mut self, post: Post) {
-        let cat_regex =
-            regex::RegexBuilder::new("\\b(cats?|kitty|kitties|kittens?|feline|catsofbluesky)\\b")
-                .case_insensitive(true)
-                .build()
-                .unwrap();
-
-        let unwanted_regex =
-            regex::RegexBuilder::new("\\b(trump|kamala|harris|biden|democrats?|democratic|republicans?|politics|dems?|aoc|GOP|vance|musk|elon|walz|fascists?|furryart|smut|furries|dnc|RFK)\\b|(right wing|left wing)")
-                .case_insensitive(true)
-                .build()
-                .unwrap();
-
-        if cat_regex.is_match(post.text.as_str())
-            && !unwanted_regex.is_match(post.text.as_str())
-            && post.labels.is_empty()
-        {
-            info!("Storing {post:?}");
-            let db = self.db.lock().await;
-
-            db.execute(
-                "INSERT OR REPLACE INTO posts (uri, text, timestamp) VALUES (?1, ?2, ?3)",
-                params![post.uri.0, post.text, post.timestamp.timestamp()],
-            )
-            .expect("Failed to insert post");
+        // Only called for posts that pass `cat_filter`
+        info!("Storing {post:?}");
+        if let Err(e) = self.store.insert_post(&post).await {
+            error!("Failed to insert post: {e}");
         }
     }
 
     async fn delete_post(&mut self, uri: Uri) {
-        let db = self.db.lock().await;
-        db.execute("DELETE FROM posts WHERE uri = ?1", params![uri.0])
-            .expect("Failed to delete post");
+        if let Err(e) = self.store.delete_post(&uri).await {
+            error!("Failed to delete post: {e}");
+        }
     }
 
     async fn like_post(&mut self, like_uri: Uri, liked_post_uri: Uri) {
-        let db = self.db.lock().await;
-        db.execute(
-            "INSERT INTO likes (post_uri, like_uri)
-             SELECT ?1, ?2
-             WHERE EXISTS (SELECT 1 FROM posts WHERE uri = ?1)",
-            params![liked_post_uri.0, like_uri.0],
-        )
-        .expect("Failed to like post");
+        if let Err(e) = self.store.like_post(&like_uri, &liked_post_uri).await {
+            error!("Failed to like post: {e}");
+        }
     }
 
     async fn delete_like(&mut self, like_uri: Uri) {
-        let db = self.db.lock().await;
-        db.execute("DELETE FROM likes WHERE like_uri = ?1", params![like_uri.0])
-            .expect("Failed to delete like");
+        if let Err(e) = self.store.delete_like(&like_uri).await {
+            error!("Failed to delete like: {e}");
+        }
     }
 
-    async fn serve_feed(&self, request: Request) -> FeedResult {
+    async fn serve_feed(&self, request: Request) -> Result<FeedResult, FeedError> {
         info!("Serving {request:?}");
 
-        let db = self.db.lock().await;
-        let mut stmt = db
-            .prepare(
-                "
-                WITH ranked_posts AS (
-                    SELECT
-                        uri,
-                        timestamp,
-                        COUNT(like_uri) AS likes
-                    FROM posts
-                    LEFT JOIN likes ON posts.uri = likes.post_uri
-                    GROUP BY posts.uri
-                    HAVING COUNT(like_uri) > 0
-                ),
-                sorted_posts AS (
-                    SELECT
-                        uri,
-                        timestamp,
-                        likes,
-                        PERCENT_RANK() OVER (ORDER BY likes DESC) AS rank
-                    FROM ranked_posts
-                )
-                SELECT uri, likes
-                FROM sorted_posts
-                WHERE rank <= 0.05
-                ORDER BY timestamp DESC;
-             ",
-            )
-            .expect("Failed to prepare statement");
-
-        let post_iter = stmt
-            .query_map([], |row| row.get::<_, String>(0))
-            .expect("Failed to query posts");
-
-        let posts: Vec<Uri> = post_iter.map(|x| x.unwrap()).map(Uri).collect();
-
-        let start_index = request
-            .cursor
-            .as_deref()
-            .and_then(|c| c.parse::<usize>().ok())
-            .unwrap_or(0);
+        // The most liked cat posts of the last day
+        let posts: Vec<Uri> = self
+            .store
+            .top_by_likes(Duration::from_secs(24 * 60 * 60), 500)
+            .await?
+            .into_iter()
+            .map(|(post, _)| post.uri)
+            .collect();
+
+        let start_index = match request.cursor.as_deref() {
+            Some(cursor) => cursor
+                .parse::<usize>()
+                .map_err(|_| FeedError::InvalidCursor)?,
+            None => 0,
+        };
         let posts_per_page = 50;
 
         let page_posts: Vec<_> = posts
@@ -146,6 +154,7 @@ impl FeedHandler for MyFeedHandler {
             .skip(start_index)
             .take(posts_per_page)
             .cloned()
+            .map(SkeletonItem::from)
             .collect();
 
         let next_cursor = if start_index + posts_per_page < posts.len() {
@@ -154,63 +163,9 @@ impl FeedHandler for MyFeedHandler {
             None
         };
 
-        FeedResult {
+        Ok(FeedResult {
             cursor: next_cursor,
             feed: page_posts,
-        }
+        })
     }
 }
-
-async fn cleanup_posts(db: &Arc<Mutex<Connection>>) {
-    const MAX_POSTS: usize = 10_000;
-
-    let cleaned_posts = db
-        .lock()
-        .await
-        .execute(
-            &format!(
-                "
-                DELETE FROM posts
-                WHERE uri NOT IN (
-                    SELECT uri
-                    FROM posts
-                    ORDER BY timestamp DESC
-                    LIMIT {MAX_POSTS}
-                );
-                "
-            ),
-            [],
-        )
-        .expect("Failed to clean up old posts");
-
-    info!("Cleaned up {cleaned_posts} posts");
-}
-
-fn initialize_db(db: &Connection) {
-    db.execute(
-        "CREATE TABLE IF NOT EXISTS posts (
-            uri TEXT PRIMARY KEY,
-            text TEXT,
-            timestamp INTEGER
-        )",
-        [],
-    )
-    .expect("Failed to create posts table");
-
-    db.execute(
-        "CREATE TABLE IF NOT EXISTS likes (
-            post_uri TEXT,
-            like_uri TEXT,
-            PRIMARY KEY (post_uri, like_uri),
-            FOREIGN KEY (post_uri) REFERENCES posts(uri) ON DELETE CASCADE
-        )",
-        [],
-    )
-    .expect("Failed to create likes table");
-
-    db.execute(
-        "CREATE INDEX IF NOT EXISTS idx_likes_post_uri ON likes(post_uri)",
-        [],
-    )
-    .expect("Failed to create index on likes.post_uri");
-}
@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::models::Did;
+use crate::utility_models::DidDocument;
+
+const PLC_DIRECTORY: &str = "https://plc.directory";
+/// How long a resolved signing key is trusted before the DID document is fetched again.
+const KEY_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+/// How long a resolved signing key is used before a token it rejects may trigger a refetch, so forged tokens can't flood DID hosts.
+const KEY_REFRESH_MIN_AGE: Duration = Duration::from_secs(60);
+/// How long fetching a DID document may take, so a slow host can't stall feed requests.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Multicodec prefixes for compressed public keys in `publicKeyMultibase`
+const SECP256K1_MULTICODEC: [u8; 2] = [0xe7, 0x01];
+const P256_MULTICODEC: [u8; 2] = [0x80, 0x24];
+
+#[derive(Debug)]
+pub(crate) enum AuthError {
+    Malformed,
+    UnsupportedAlgorithm(String),
+    WrongAudience(String),
+    Expired,
+    WrongMethod(String),
+    Resolution(String),
+    MissingSigningKey,
+    InvalidSignature,
+}
+
+impl Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::Malformed => write!(f, "Malformed authorization token"),
+            AuthError::UnsupportedAlgorithm(alg) => {
+                write!(f, "Unsupported signing algorithm {alg}")
+            }
+            AuthError::WrongAudience(aud) => write!(f, "Token audience {aud} is not this service"),
+            AuthError::Expired => write!(f, "Token has expired"),
+            AuthError::WrongMethod(lxm) => write!(f, "Token is not valid for {lxm}"),
+            AuthError::Resolution(reason) => write!(f, "Couldn't resolve issuer: {reason}"),
+            AuthError::MissingSigningKey => write!(f, "Issuer has no usable signing key"),
+            AuthError::InvalidSignature => write!(f, "Token signature is invalid"),
+        }
+    }
+}
+
+/// Resolves DID documents for token issuers.
+pub(crate) trait DidResolver {
+    fn resolve(
+        &self,
+        did: &str,
+    ) -> impl std::future::Future<Output = Result<DidDocument, AuthError>> + Send;
+}
+
+/// Resolves `did:plc` documents through the PLC directory & `did:web` documents from the domain's `.well-known` path.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpDidResolver {
+    client: reqwest::Client,
+}
+
+impl Default for HttpDidResolver {
+    fn default() -> Self {
+        HttpDidResolver {
+            client: reqwest::Client::builder()
+                .timeout(RESOLVE_TIMEOUT)
+                .build()
+                .expect("HTTP client config is valid"),
+        }
+    }
+}
+
+impl DidResolver for HttpDidResolver {
+    async fn resolve(&self, did: &str) -> Result<DidDocument, AuthError> {
+        self.client
+            .get(did_document_url(did)?)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| AuthError::Resolution(e.to_string()))?
+            .json::<DidDocument>()
+            .await
+            .map_err(|e| AuthError::Resolution(e.to_string()))
+    }
+}
+
+/// Where the DID document of a `did:plc` or `did:web` is published
+fn did_document_url(did: &str) -> Result<String, AuthError> {
+    if did.starts_with("did:plc:") {
+        Ok(format!("{PLC_DIRECTORY}/{did}"))
+    } else if let Some(host) = did.strip_prefix("did:web:") {
+        // A port is percent-encoded in the DID, like `did:web:localhost%3A8443`
+        let host = host.replace("%3A", ":").replace("%3a", ":");
+        Ok(format!("https://{host}/.well-known/did.json"))
+    } else {
+        Err(AuthError::Resolution(format!(
+            "Unsupported DID method {did}"
+        )))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PublicKey {
+    K256(k256::ecdsa::VerifyingKey),
+    P256(p256::ecdsa::VerifyingKey),
+}
+
+impl PublicKey {
+    fn from_did_document(document: &DidDocument) -> Result<Self, AuthError> {
+        let multibase = document
+            .verification_method
+            .iter()
+            .find(|method| {
+                method.id == "#atproto" || method.id == format!("{}#atproto", document.id)
+            })
+            .and_then(|method| method.public_key_multibase.as_ref())
+            .ok_or(AuthError::MissingSigningKey)?;
+        let (_, bytes) = multibase::decode(multibase).map_err(|_| AuthError::MissingSigningKey)?;
+        if let Some(key) = bytes.strip_prefix(&SECP256K1_MULTICODEC) {
+            k256::ecdsa::VerifyingKey::from_sec1_bytes(key)
+                .map(PublicKey::K256)
+                .map_err(|_| AuthError::MissingSigningKey)
+        } else if let Some(key) = bytes.strip_prefix(&P256_MULTICODEC) {
+            p256::ecdsa::VerifyingKey::from_sec1_bytes(key)
+                .map(PublicKey::P256)
+                .map_err(|_| AuthError::MissingSigningKey)
+        } else {
+            Err(AuthError::MissingSigningKey)
+        }
+    }
+
+    fn verify(&self, alg: &str, message: &[u8], signature: &[u8]) -> Result<(), AuthError> {
+        use k256::ecdsa::signature::Verifier;
+        match (self, alg) {
+            (PublicKey::K256(key), "ES256K") => {
+                let signature = k256::ecdsa::Signature::from_slice(signature)
+                    .map_err(|_| AuthError::InvalidSignature)?;
+                key.verify(message, &signature)
+                    .map_err(|_| AuthError::InvalidSignature)
+            }
+            (PublicKey::P256(key), "ES256") => {
+                let signature = p256::ecdsa::Signature::from_slice(signature)
+                    .map_err(|_| AuthError::InvalidSignature)?;
+                // Only low-S signatures are valid in atproto
+                if signature.normalize_s().is_some() {
+                    return Err(AuthError::InvalidSignature);
+                }
+                key.verify(message, &signature)
+                    .map_err(|_| AuthError::InvalidSignature)
+            }
+            _ => Err(AuthError::InvalidSignature),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+    aud: String,
+    exp: i64,
+    lxm: Option<String>,
+}
+
+/// Verifies inter-service JWTs sent by the AppView on behalf of a viewer.
+#[derive(Debug, Clone)]
+pub(crate) struct ServiceAuth<Resolver: DidResolver> {
+    service_did: String,
+    resolver: Resolver,
+    keys: Arc<Mutex<HashMap<String, (Instant, PublicKey)>>>,
+}
+
+impl<Resolver: DidResolver> ServiceAuth<Resolver> {
+    pub(crate) fn new(service_did: String, resolver: Resolver) -> Self {
+        ServiceAuth {
+            service_did,
+            resolver,
+            keys: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Verifies the value of an `Authorization` header for a call to the XRPC method `lxm`.
+    ///
+    /// Returns the DID of the viewer the token was issued for.
+    pub(crate) async fn verify(&self, authorization: &str, lxm: &str) -> Result<Did, AuthError> {
+        let token = authorization
+            .strip_prefix("Bearer ")
+            .ok_or(AuthError::Malformed)?
+            .trim();
+        let mut parts = token.split('.');
+        let (Some(header), Some(claims), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(AuthError::Malformed);
+        };
+        let signed = &token[..header.len() + 1 + claims.len()];
+        let header: Header = decode_json(header)?;
+        let claims: Claims = decode_json(claims)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| AuthError::Malformed)?;
+
+        if header.alg != "ES256K" && header.alg != "ES256" {
+            return Err(AuthError::UnsupportedAlgorithm(header.alg));
+        }
+        if claims.aud != self.service_did {
+            return Err(AuthError::WrongAudience(claims.aud));
+        }
+        if claims.exp <= Utc::now().timestamp() {
+            return Err(AuthError::Expired);
+        }
+        if let Some(token_lxm) = claims.lxm {
+            if token_lxm != lxm {
+                return Err(AuthError::WrongMethod(token_lxm));
+            }
+        }
+
+        let issuer = claims.iss.split('#').next().unwrap_or_default().to_string();
+        let cached = self.keys.lock().unwrap().get(&issuer).copied();
+        if let Some((fetched, key)) = cached {
+            let verified = key.verify(&header.alg, signed.as_bytes(), &signature);
+            if fetched.elapsed() < KEY_CACHE_TTL && verified.is_ok() {
+                return Ok(Did(issuer));
+            }
+            if fetched.elapsed() < KEY_REFRESH_MIN_AGE {
+                return verified.map(|_| Did(issuer));
+            }
+        }
+        // The issuer may have rotated keys since the cached document was fetched
+        let key = PublicKey::from_did_document(&self.resolver.resolve(&issuer).await?)?;
+        self.keys
+            .lock()
+            .unwrap()
+            .insert(issuer.clone(), (Instant::now(), key));
+        key.verify(&header.alg, signed.as_bytes(), &signature)?;
+        Ok(Did(issuer))
+    }
+}
+
+fn decode_json<T: serde::de::DeserializeOwned>(part: &str) -> Result<T, AuthError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(part)
+        .map_err(|_| AuthError::Malformed)?;
+    serde_json::from_slice(&bytes).map_err(|_| AuthError::Malformed)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::utility_models::VerificationMethod;
+    use k256::ecdsa::signature::Signer;
+    use rand::rngs::OsRng;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    pub(crate) const SERVICE_DID: &str = "did:web:feeds.example.com";
+    pub(crate) const VIEWER_DID: &str = "did:plc:viewer";
+    const LXM: &str = "app.bsky.feed.getFeedSkeleton";
+
+    #[derive(Clone)]
+    pub(crate) struct StubResolver {
+        multibase: String,
+        resolutions: Arc<AtomicUsize>,
+    }
+
+    impl DidResolver for StubResolver {
+        async fn resolve(&self, did: &str) -> Result<DidDocument, AuthError> {
+            self.resolutions.fetch_add(1, Ordering::Relaxed);
+            if did != VIEWER_DID {
+                return Err(AuthError::Resolution(format!("Unknown DID {did}")));
+            }
+            Ok(DidDocument {
+                context: vec![],
+                id: did.to_string(),
+                service: vec![],
+                verification_method: vec![VerificationMethod {
+                    id: format!("{did}#atproto"),
+                    type_: "Multikey".to_string(),
+                    controller: did.to_string(),
+                    public_key_multibase: Some(self.multibase.clone()),
+                }],
+            })
+        }
+    }
+
+    pub(crate) fn k256_resolver(key: &k256::ecdsa::SigningKey) -> StubResolver {
+        let public = key.verifying_key().to_encoded_point(true);
+        StubResolver {
+            multibase: multibase::encode(
+                multibase::Base::Base58Btc,
+                [&SECP256K1_MULTICODEC[..], public.as_bytes()].concat(),
+            ),
+            resolutions: Arc::default(),
+        }
+    }
+
+    fn p256_resolver(key: &p256::ecdsa::SigningKey) -> StubResolver {
+        let public = key.verifying_key().to_encoded_point(true);
+        StubResolver {
+            multibase: multibase::encode(
+                multibase::Base::Base58Btc,
+                [&P256_MULTICODEC[..], public.as_bytes()].concat(),
+            ),
+            resolutions: Arc::default(),
+        }
+    }
+
+    fn unsigned_token(alg: &str, claims: serde_json::Value) -> String {
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(json!({ "alg": alg, "typ": "JWT" }).to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string()),
+        )
+    }
+
+    pub(crate) fn k256_token(key: &k256::ecdsa::SigningKey, claims: serde_json::Value) -> String {
+        let unsigned = unsigned_token("ES256K", claims);
+        let signature: k256::ecdsa::Signature = key.sign(unsigned.as_bytes());
+        format!(
+            "Bearer {unsigned}.{}",
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        )
+    }
+
+    fn p256_token(key: &p256::ecdsa::SigningKey, claims: serde_json::Value) -> String {
+        let unsigned = unsigned_token("ES256", claims);
+        let signature: p256::ecdsa::Signature = key.sign(unsigned.as_bytes());
+        let signature = signature.normalize_s().unwrap_or(signature);
+        format!(
+            "Bearer {unsigned}.{}",
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        )
+    }
+
+    pub(crate) fn valid_claims() -> serde_json::Value {
+        json!({
+            "iss": VIEWER_DID,
+            "aud": SERVICE_DID,
+            "exp": Utc::now().timestamp() + 60,
+            "lxm": LXM,
+        })
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_k256_token() {
+        let key = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let auth = ServiceAuth::new(SERVICE_DID.to_string(), k256_resolver(&key));
+        let viewer = auth
+            .verify(&k256_token(&key, valid_claims()), LXM)
+            .await
+            .unwrap();
+        assert_eq!(viewer.0, VIEWER_DID);
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_p256_token() {
+        let key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let auth = ServiceAuth::new(SERVICE_DID.to_string(), p256_resolver(&key));
+        let viewer = auth
+            .verify(&p256_token(&key, valid_claims()), LXM)
+            .await
+            .unwrap();
+        assert_eq!(viewer.0, VIEWER_DID);
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_audience() {
+        let key = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let auth = ServiceAuth::new(SERVICE_DID.to_string(), k256_resolver(&key));
+        let mut claims = valid_claims();
+        claims["aud"] = json!("did:web:other.example.com");
+        let result = auth.verify(&k256_token(&key, claims), LXM).await;
+        assert!(matches!(result, Err(AuthError::WrongAudience(_))));
+    }
+
+    #[tokio::test]
+    async fn rejects_expired_token() {
+        let key = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let auth = ServiceAuth::new(SERVICE_DID.to_string(), k256_resolver(&key));
+        let mut claims = valid_claims();
+        claims["exp"] = json!(Utc::now().timestamp() - 60);
+        let result = auth.verify(&k256_token(&key, claims), LXM).await;
+        assert!(matches!(result, Err(AuthError::Expired)));
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_method() {
+        let key = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let auth = ServiceAuth::new(SERVICE_DID.to_string(), k256_resolver(&key));
+        let mut claims = valid_claims();
+        claims["lxm"] = json!("app.bsky.feed.getTimeline");
+        let result = auth.verify(&k256_token(&key, claims), LXM).await;
+        assert!(matches!(result, Err(AuthError::WrongMethod(_))));
+    }
+
+    #[tokio::test]
+    async fn rejects_token_signed_by_another_key() {
+        let key = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let other_key = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let auth = ServiceAuth::new(SERVICE_DID.to_string(), k256_resolver(&key));
+        let result = auth
+            .verify(&k256_token(&other_key, valid_claims()), LXM)
+            .await;
+        assert!(matches!(result, Err(AuthError::InvalidSignature)));
+    }
+
+    #[tokio::test]
+    async fn doesnt_refetch_keys_for_every_forged_token() {
+        let key = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let forger = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let resolver = k256_resolver(&key);
+        let auth = ServiceAuth::new(SERVICE_DID.to_string(), resolver.clone());
+        auth.verify(&k256_token(&key, valid_claims()), LXM)
+            .await
+            .unwrap();
+        for _ in 0..10 {
+            let result = auth.verify(&k256_token(&forger, valid_claims()), LXM).await;
+            assert!(matches!(result, Err(AuthError::InvalidSignature)));
+        }
+        assert_eq!(resolver.resolutions.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn builds_did_document_urls() {
+        assert_eq!(
+            did_document_url("did:plc:viewer").unwrap(),
+            "https://plc.directory/did:plc:viewer"
+        );
+        assert_eq!(
+            did_document_url("did:web:localhost%3A8443").unwrap(),
+            "https://localhost:8443/.well-known/did.json"
+        );
+        assert!(did_document_url("did:key:z6Mk").is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_token() {
+        let key = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let auth = ServiceAuth::new(SERVICE_DID.to_string(), k256_resolver(&key));
+        let result = auth.verify("Bearer not-a-jwt", LXM).await;
+        assert!(matches!(result, Err(AuthError::Malformed)));
+    }
+}
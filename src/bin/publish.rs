@@ -36,6 +36,10 @@ struct Args {
     #[arg(long)]
     app_password: String,
 
+    /// Lets clients send feedback, such as "show more" & "show less", to your feed's `FeedHandler::on_interactions`
+    #[arg(long)]
+    accepts_interactions: bool,
+
     /// Your feed's hostname. The public-facing domain name where the service is accessible
     /// For example, the `my.domain.name` in `https://my.domain.name`
     #[arg(long)]
@@ -108,7 +112,7 @@ async fn main() {
             InputData {
                 collection: Nsid::new("app.bsky.feed.generator".to_owned()).unwrap(),
                 record: RecordData {
-                    accepts_interactions: None,
+                    accepts_interactions: args.accepts_interactions.then_some(true),
                     #[allow(unreachable_code)]
                     avatar: avatar.map(|a| a.blob.clone()),
                     content_mode: None,
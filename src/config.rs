@@ -1,6 +1,11 @@
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
 
 use dotenv::dotenv;
+use jetstream_oxide::DefaultJetstreamEndpoints;
+
+use crate::cursor_store::CursorStore;
 
 #[derive(Debug, Clone)]
 /// Configuration values for a Feed service
@@ -17,19 +22,82 @@ pub struct Config {
     /// - Is secured with SSL (HTTPS).
     /// - Is accessible on the public internet.
     pub feed_generator_hostname: String,
+    /// Persists the firehose cursor. When set, the firehose resumes from the saved cursor on startup instead of only receiving new events.
+    ///
+    /// See `FileCursorStore` & `MemoryCursorStore`
+    pub cursor_store: Option<Arc<dyn CursorStore>>,
+    /// How far to rewind the saved cursor when resuming.
+    ///
+    /// Replaying a few seconds of events makes sure nothing processed around a restart is missed. Handlers may see some events twice.
+    pub cursor_rewind: Duration,
+    /// Jetstream websocket endpoints to receive firehose events from, for example `wss://jetstream1.us-east.bsky.network/subscribe`
+    ///
+    /// Endpoints are tried in order, moving on to the next endpoint each time the connection drops.
+    /// This can point to a self-hosted Jetstream instance or a local test server.
+    ///
+    /// Defaults to the public Jetstream instances run by Bluesky.
+    pub jetstream_endpoints: Vec<String>,
+    /// Requests zstd compressed events from Jetstream, which are roughly half the size of uncompressed events.
+    pub jetstream_compression: bool,
+    /// The collection NSIDs to receive firehose events for. Receives events for *all* collections when empty.
+    ///
+    /// Defaults to the collections with a `FeedHandler` callback: posts, likes, reposts, follows & blocks.
+    pub wanted_collections: Vec<String>,
+    /// Only receive firehose events from these accounts' DIDs. Receives events from *all* accounts when empty.
+    ///
+    /// Useful for feeds that only follow a fixed set of accounts. Jetstream accepts up to 10,000 DIDs.
+    pub wanted_dids: Vec<String>,
+    /// How long the firehose can go without delivering an event before `/ready` reports the feed as not ready.
+    ///
+    /// Raise this when `wanted_collections` or `wanted_dids` only match infrequent events.
+    pub firehose_stall_timeout: Duration,
 }
 
 impl Config {
+    /// Creates a config with default firehose settings
+    pub fn new(
+        publisher_did: impl Into<String>,
+        feed_generator_hostname: impl Into<String>,
+    ) -> Self {
+        Config {
+            publisher_did: publisher_did.into(),
+            feed_generator_hostname: feed_generator_hostname.into(),
+            cursor_store: None,
+            cursor_rewind: Duration::from_secs(5),
+            jetstream_endpoints: [
+                DefaultJetstreamEndpoints::USEastOne,
+                DefaultJetstreamEndpoints::USEastTwo,
+                DefaultJetstreamEndpoints::USWestOne,
+                DefaultJetstreamEndpoints::USWestTwo,
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            jetstream_compression: true,
+            wanted_collections: [
+                "app.bsky.feed.post",
+                "app.bsky.feed.like",
+                "app.bsky.feed.repost",
+                "app.bsky.graph.follow",
+                "app.bsky.graph.block",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            wanted_dids: vec![],
+            firehose_stall_timeout: Duration::from_secs(60),
+        }
+    }
+
     /// Loads the config from a local .env file containing these variables
     /// PUBLISHER_DID
     /// FEED_GENERATOR_HOSTNAME
     pub fn load_env_config() -> Self {
         dotenv().expect("Missing .env");
-        Config {
-            publisher_did: env::var("PUBLISHER_DID")
-                .expect(".env file is missing an entry for PUBLISHER_DID"),
-            feed_generator_hostname: env::var("FEED_GENERATOR_HOSTNAME")
+        Config::new(
+            env::var("PUBLISHER_DID").expect(".env file is missing an entry for PUBLISHER_DID"),
+            env::var("FEED_GENERATOR_HOSTNAME")
                 .expect(".env file is missing an entry for FEED_GENERATOR_HOSTNAME"),
-        }
+        )
     }
 }
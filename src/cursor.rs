@@ -0,0 +1,222 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::models::{FeedError, FeedResult, Post, Request, SkeletonItem, Uri};
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 100;
+const MAC_LENGTH: usize = 32;
+
+/// A position in a feed sorted newest first: the timestamp & URI of the last item on a page.
+///
+/// The URI breaks ties between items with the same timestamp, so pages stay stable as new posts arrive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedPosition {
+    pub timestamp: DateTime<Utc>,
+    pub uri: Uri,
+}
+
+impl FeedPosition {
+    pub fn new(timestamp: DateTime<Utc>, uri: Uri) -> Self {
+        FeedPosition { timestamp, uri }
+    }
+
+    /// The position of a post in a feed sorted by post timestamp
+    pub fn of(post: &Post) -> Self {
+        FeedPosition::new(post.timestamp, post.uri.clone())
+    }
+
+    /// Returns true if this position comes after `other` in a feed sorted newest first
+    fn is_after(&self, other: &FeedPosition) -> bool {
+        (self.timestamp, &self.uri.0) < (other.timestamp, &other.uri.0)
+    }
+}
+
+/// Encodes `FeedPosition`s as opaque URL-safe base64 strings for `FeedResult::cursor` & decodes them from `Request::cursor`.
+///
+/// With an HMAC key, cursors are signed & cursors that were tampered with are rejected as `FeedError::InvalidCursor`.
+#[derive(Clone, Default)]
+pub struct CursorCodec {
+    key: Option<Vec<u8>>,
+}
+
+impl std::fmt::Debug for CursorCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CursorCodec")
+            .field("signed", &self.key.is_some())
+            .finish()
+    }
+}
+
+impl CursorCodec {
+    /// A codec for unsigned cursors
+    pub fn new() -> Self {
+        CursorCodec { key: None }
+    }
+
+    /// A codec that signs cursors with HMAC-SHA256 using `key`
+    pub fn with_hmac_key(key: impl Into<Vec<u8>>) -> Self {
+        CursorCodec {
+            key: Some(key.into()),
+        }
+    }
+
+    pub fn encode(&self, cursor: &FeedPosition) -> String {
+        let mut bytes =
+            format!("{}::{}", cursor.timestamp.timestamp_micros(), cursor.uri.0).into_bytes();
+        if let Some(mac) = self.mac(&bytes) {
+            bytes.extend(mac.finalize().into_bytes());
+        }
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    pub fn decode(&self, cursor: &str) -> Result<FeedPosition, FeedError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .map_err(|_| FeedError::InvalidCursor)?;
+        let payload = match self.key {
+            Some(_) => {
+                let split = bytes
+                    .len()
+                    .checked_sub(MAC_LENGTH)
+                    .ok_or(FeedError::InvalidCursor)?;
+                let (payload, signature) = bytes.split_at(split);
+                self.mac(payload)
+                    .expect("Codec has a key")
+                    .verify_slice(signature)
+                    .map_err(|_| FeedError::InvalidCursor)?;
+                payload
+            }
+            None => &bytes,
+        };
+        let (time_us, uri) = std::str::from_utf8(payload)
+            .ok()
+            .and_then(|payload| payload.split_once("::"))
+            .ok_or(FeedError::InvalidCursor)?;
+        let timestamp = time_us
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or(FeedError::InvalidCursor)?;
+        Ok(FeedPosition::new(timestamp, Uri(uri.to_string())))
+    }
+
+    /// Serves a page of `items` for `request` using keyset pagination.
+    ///
+    /// `items` must be sorted newest first, with ties sorted by URI descending.
+    /// The page starts after the request's cursor & holds up to `request.limit` items, 50 by default.
+    pub fn paginate(
+        &self,
+        request: &Request,
+        items: impl IntoIterator<Item = FeedPosition>,
+    ) -> Result<FeedResult, FeedError> {
+        let after = request
+            .cursor
+            .as_deref()
+            .map(|cursor| self.decode(cursor))
+            .transpose()?;
+        let limit = request
+            .limit
+            .map_or(DEFAULT_LIMIT, usize::from)
+            .clamp(1, MAX_LIMIT);
+        let mut items = items
+            .into_iter()
+            .filter(|item| after.as_ref().is_none_or(|after| item.is_after(after)))
+            .peekable();
+        let mut page = Vec::with_capacity(limit);
+        let mut last = None;
+        while page.len() < limit {
+            let Some(item) = items.next() else {
+                break;
+            };
+            page.push(SkeletonItem::from(item.uri.clone()));
+            last = Some(item);
+        }
+        let cursor = match (items.peek(), last) {
+            (Some(_), Some(last)) => Some(self.encode(&last)),
+            _ => None,
+        };
+        Ok(FeedResult { cursor, feed: page })
+    }
+
+    fn mac(&self, payload: &[u8]) -> Option<Hmac<Sha256>> {
+        let key = self.key.as_ref()?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+        mac.update(payload);
+        Some(mac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(time_us: i64, rkey: &str) -> FeedPosition {
+        FeedPosition::new(
+            DateTime::from_timestamp_micros(time_us).unwrap(),
+            Uri(format!("at://did:plc:alice/app.bsky.feed.post/{rkey}")),
+        )
+    }
+
+    fn request(cursor: Option<String>, limit: u8) -> Request {
+        Request {
+            cursor,
+            feed: "at://did:plc:publisher/app.bsky.feed.generator/cats".to_string(),
+            limit: Some(limit),
+            viewer: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_signed_cursors() {
+        let codec = CursorCodec::with_hmac_key("secret");
+        let cursor = item(1_700_000_000_000_000, "3k2a");
+        assert_eq!(codec.decode(&codec.encode(&cursor)).unwrap(), cursor);
+
+        let mut tampered = URL_SAFE_NO_PAD.decode(codec.encode(&cursor)).unwrap();
+        tampered[0] ^= 1;
+        assert!(matches!(
+            codec.decode(&URL_SAFE_NO_PAD.encode(tampered)),
+            Err(FeedError::InvalidCursor)
+        ));
+        assert!(matches!(
+            CursorCodec::with_hmac_key("other").decode(&codec.encode(&cursor)),
+            Err(FeedError::InvalidCursor)
+        ));
+        assert!(matches!(
+            codec.decode("not a cursor"),
+            Err(FeedError::InvalidCursor)
+        ));
+    }
+
+    #[test]
+    fn pages_stay_stable_when_posts_arrive() {
+        let codec = CursorCodec::new();
+        let mut items = vec![item(3, "c"), item(2, "b2"), item(2, "b1"), item(1, "a")];
+
+        let first = codec.paginate(&request(None, 2), items.clone()).unwrap();
+        assert_eq!(first.feed.len(), 2);
+
+        // A newer post arrives between requests
+        items.insert(0, item(4, "d"));
+        let second = codec
+            .paginate(&request(first.cursor, 2), items.clone())
+            .unwrap();
+        let uris: Vec<_> = second
+            .feed
+            .iter()
+            .map(|item| item.post.0.as_str())
+            .collect();
+        assert_eq!(
+            uris,
+            vec![
+                "at://did:plc:alice/app.bsky.feed.post/b1",
+                "at://did:plc:alice/app.bsky.feed.post/a",
+            ]
+        );
+        assert_eq!(second.cursor, None);
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt::Debug;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use tracing::error;
+
+/// Persists the firehose cursor so the firehose can resume where it left off after a restart.
+///
+/// The cursor is the `time_us` of the most recently processed firehose event, in microseconds since the unix epoch.
+pub trait CursorStore: Debug + Send + Sync {
+    /// Loads the saved cursor, if there is one.
+    fn load(&self) -> Option<u64>;
+    /// Saves the cursor, replacing any previously saved value.
+    fn save(&self, time_us: u64);
+}
+
+/// Stores the cursor in a local file.
+#[derive(Debug, Clone)]
+pub struct FileCursorStore {
+    path: PathBuf,
+}
+
+impl FileCursorStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileCursorStore { path: path.into() }
+    }
+}
+
+impl CursorStore for FileCursorStore {
+    fn load(&self) -> Option<u64> {
+        let contents = fs::read_to_string(&self.path).ok()?;
+        match contents.trim().parse() {
+            Ok(time_us) => Some(time_us),
+            Err(e) => {
+                error!(path = ?self.path, error = %e, "Invalid saved cursor");
+                None
+            }
+        }
+    }
+
+    fn save(&self, time_us: u64) {
+        // Write to a temporary file first so a crash mid-write can't corrupt the saved cursor
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        if let Err(e) = fs::write(&temp_path, time_us.to_string())
+            .and_then(|_| fs::rename(&temp_path, &self.path))
+        {
+            error!(path = ?self.path, error = %e, "Failed to save cursor");
+        }
+    }
+}
+
+/// Stores the cursor in memory. The cursor is shared between clones & lost when the process exits.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryCursorStore {
+    cursor: Arc<Mutex<Option<u64>>>,
+}
+
+impl CursorStore for MemoryCursorStore {
+    fn load(&self) -> Option<u64> {
+        *self.cursor.lock().unwrap()
+    }
+
+    fn save(&self, time_us: u64) {
+        *self.cursor.lock().unwrap() = Some(time_us);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path in a fresh temporary directory, removed when the test ends
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(test: &str, file_name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("skyfeed-{}-{test}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            TempPath(dir.join(file_name))
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            fs::remove_dir_all(self.0.parent().unwrap()).ok();
+        }
+    }
+
+    #[test]
+    fn round_trips_cursor_through_file() {
+        let path = TempPath::new("round_trip", "cursor");
+        let store = FileCursorStore::new(&path.0);
+        assert_eq!(store.load(), None);
+
+        store.save(1_700_000_000_000_000);
+        store.save(1_700_000_000_000_001);
+        assert_eq!(store.load(), Some(1_700_000_000_000_001));
+        assert_eq!(
+            FileCursorStore::new(&path.0).load(),
+            Some(1_700_000_000_000_001)
+        );
+    }
+
+    #[test]
+    fn ignores_corrupt_cursor_file() {
+        let path = TempPath::new("corrupt", "cursor");
+        fs::write(&path.0, "not a cursor").unwrap();
+        assert_eq!(FileCursorStore::new(&path.0).load(), None);
+    }
+
+    #[test]
+    fn writes_temporary_file_next_to_cursor_file() {
+        // Replacing the extension would make the temporary file the cursor file itself
+        let path = TempPath::new("temp_file", "cursor.tmp");
+        let store = FileCursorStore::new(&path.0);
+        store.save(42);
+        assert_eq!(store.load(), Some(42));
+        assert!(!path.0.with_file_name("cursor.tmp.tmp").exists());
+    }
+}
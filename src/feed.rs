@@ -1,34 +1,67 @@
+use atrium_api::app::bsky::feed::defs::{
+    SkeletonFeedPostData, SkeletonFeedPostReasonRefs, SkeletonReasonRepostData,
+};
 use atrium_api::app::bsky::feed::describe_feed_generator::{
     FeedData, OutputData as FeedGeneratorDescription,
 };
 use atrium_api::app::bsky::feed::get_feed_skeleton::OutputData as FeedSkeleton;
 use atrium_api::app::bsky::feed::get_feed_skeleton::Parameters as FeedSkeletonQuery;
 use atrium_api::app::bsky::feed::get_feed_skeleton::ParametersData as FeedSkeletonParameters;
+use atrium_api::app::bsky::feed::send_interactions::{
+    Input as SendInteractionsInput, OutputData as SendInteractionsOutput,
+};
 use atrium_api::record::KnownRecord;
-use atrium_api::types::Object;
-use chrono::DateTime;
-use env_logger::Env;
-use jetstream_oxide::exports::Nsid;
+use atrium_api::types::{Object, Union};
+use chrono::{DateTime, Utc};
+use jetstream_oxide::exports::{Did as JetstreamDid, Nsid};
 use jetstream_oxide::{
     events::{
         commit::{CommitData, CommitEvent, CommitInfo, CommitType},
-        JetstreamEvent::Commit,
+        JetstreamEvent::{self, Commit},
     },
-    DefaultJetstreamEndpoints, JetstreamCompression, JetstreamConfig, JetstreamConnector,
+    JetstreamCompression, JetstreamConfig, JetstreamConnector,
 };
-use log::{error, info};
 use std::fmt::Debug;
 use std::net::SocketAddr;
-use warp::Filter;
+use std::time::{Duration, Instant};
+#[cfg(unix)]
+use tokio::signal::unix::SignalKind;
+use tokio::sync::{oneshot, watch};
+use tracing::{debug_span, error, field, info, info_span, warn, Instrument, Span};
+use warp::http::StatusCode;
+use warp::{Filter, Reply as _};
 
-use crate::models::{Did, Embed, Label, Post, Request, Uri};
-use crate::utility_models::{DidDocument, Service};
+use crate::auth::{AuthError, DidResolver, HttpDidResolver, ServiceAuth};
+use crate::feed_router::FeedRouter;
+use crate::health::FirehoseHealth;
+use crate::models::{
+    AtUri, Did, Embed, Facet, FeedError, Interaction, Label, Post, Reply, Request, Uri,
+};
+use crate::utility_models::{DidDocument, Service, XrpcError};
 use crate::Cid;
 use crate::{config::Config, feed_handler::FeedHandler};
 
+/// How often the firehose cursor is saved to the configured `CursorStore`
+const CURSOR_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+/// Interaction batches from clients are small, this only guards against oversized requests
+const MAX_INTERACTIONS_BODY_BYTES: u64 = 256 * 1024;
+/// Delay before reconnecting after the first failed firehose connection attempt
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+
 /// A `Feed` stores a `FeedHandler`, handles feed server endpoints & connects to the Firehose using the `start` methods.
+///
+/// Several feeds can be served from one feed generator service using the `start_feeds` methods.
 pub trait Feed<Handler: FeedHandler + Clone + Send + Sync + 'static> {
     fn handler(&mut self) -> Handler;
+    /// Completes when the feed should shut down.
+    ///
+    /// On shutdown the firehose is disconnected, already received events are handled, `FeedHandler::shutdown` is called, the cursor is saved & finally the server stops.
+    ///
+    /// Defaults to shutting down on Ctrl+C, or SIGTERM on unix.
+    fn shutdown_signal(&mut self) -> impl std::future::Future<Output = ()> + Send + 'static {
+        termination_signal()
+    }
     /// Starts the feed generator server & connects to the firehose.
     ///
     /// This method loads the config from a local .env file using `dotenv`. See `Config`
@@ -38,7 +71,7 @@ pub trait Feed<Handler: FeedHandler + Clone + Send + Sync + 'static> {
     ///
     /// # Panics
     ///
-    /// Panics if unable to bind to the provided address.
+    /// Panics if unable to bind to the provided address, or if the firehose settings in `Config` are invalid.
     fn start(
         &mut self,
         name: impl AsRef<str>,
@@ -54,19 +87,58 @@ pub trait Feed<Handler: FeedHandler + Clone + Send + Sync + 'static> {
     ///
     /// # Panics
     ///
-    /// Panics if unable to bind to the provided address.
+    /// Panics if unable to bind to the provided address, or if the firehose settings in `Config` are invalid.
     fn start_with_config(
         &mut self,
         name: impl AsRef<str>,
         config: Config,
         address: impl Into<SocketAddr> + Debug + Clone + Send,
     ) -> impl std::future::Future<Output = ()> + Send {
-        let mut handler = self.handler();
-        let address = address.clone();
-        let feed_name = name.as_ref().to_string();
+        let handler = self.handler();
+        self.start_feeds_with_config([(name, handler)], config, address)
+    }
+    /// Starts a feed generator server serving several feeds & connects to the firehose.
+    ///
+    /// This method loads the config from a local .env file using `dotenv`. See `Config`
+    ///
+    /// - feeds: The identifying name of each feed paired with its handler. Every handler receives all firehose input, feed requests are routed to the handler of the requested feed.
+    /// - address: The address to bind the server to
+    ///
+    /// # Panics
+    ///
+    /// Panics if unable to bind to the provided address, or if the firehose settings in `Config` are invalid.
+    fn start_feeds(
+        &mut self,
+        feeds: impl IntoIterator<Item = (impl AsRef<str>, Handler)>,
+        address: impl Into<SocketAddr> + Debug + Clone + Send,
+    ) -> impl std::future::Future<Output = ()> + Send {
+        self.start_feeds_with_config(feeds, Config::load_env_config(), address)
+    }
+    /// Starts a feed generator server serving several feeds & connects to the firehose.
+    ///
+    /// - feeds: The identifying name of each feed paired with its handler. Every handler receives all firehose input, feed requests are routed to the handler of the requested feed.
+    /// - config: Configuration values, see `Config`
+    /// - address: The address to bind the server to
+    ///
+    /// # Panics
+    ///
+    /// Panics if unable to bind to the provided address, or if the firehose settings in `Config` are invalid.
+    fn start_feeds_with_config(
+        &mut self,
+        feeds: impl IntoIterator<Item = (impl AsRef<str>, Handler)>,
+        config: Config,
+        address: impl Into<SocketAddr> + Debug + Clone + Send,
+    ) -> impl std::future::Future<Output = ()> + Send {
+        let handler = FeedRouter::new(
+            &config.publisher_did,
+            feeds
+                .into_iter()
+                .map(|(name, handler)| (name.as_ref().to_string(), handler))
+                .collect(),
+        );
+        let address: SocketAddr = address.into();
+        let shutdown_signal = self.shutdown_signal();
         async move {
-            env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
-
             let config = config;
 
             let did_config = config.clone();
@@ -76,171 +148,504 @@ pub trait Feed<Handler: FeedHandler + Clone + Send + Sync + 'static> {
                 .and_then(move || did_json(did_config.clone()));
 
             let describe_feed_config = config.clone();
+            let feed_uris: Vec<String> = handler.feed_uris().map(str::to_string).collect();
             let describe_feed_generator = warp::path("xrpc")
                 .and(warp::path("app.bsky.feed.describeFeedGenerator"))
                 .and(warp::get())
                 .and_then(move || {
-                    describe_feed_generator(describe_feed_config.clone(), feed_name.clone())
+                    describe_feed_generator(describe_feed_config.clone(), feed_uris.clone())
                 });
 
             let get_feed_handler = handler.clone();
+            let auth = ServiceAuth::new(
+                format!("did:web:{}", config.feed_generator_hostname),
+                HttpDidResolver::default(),
+            );
             let get_feed_skeleton = warp::path("xrpc")
                 .and(warp::path("app.bsky.feed.getFeedSkeleton"))
                 .and(warp::get())
                 .and(warp::query::<FeedSkeletonParameters>())
-                .and_then(move |query: FeedSkeletonParameters| {
-                    get_feed_skeleton::<Handler>(query.into(), get_feed_handler.clone())
+                .and(warp::header::optional::<String>("authorization"))
+                .and_then({
+                    let auth = auth.clone();
+                    move |query: FeedSkeletonParameters, authorization: Option<String>| {
+                        get_feed_skeleton(
+                            query.into(),
+                            authorization,
+                            auth.clone(),
+                            get_feed_handler.clone(),
+                        )
+                    }
                 });
 
-            let api = did_json.or(describe_feed_generator).or(get_feed_skeleton);
-
-            info!("Serving feed on {}", format!("{:?}", address));
-
-            let routes = api.with(warp::log::custom(|info| {
-                let method = info.method();
-                let path = info.path();
-                let status = info.status();
-                let elapsed = info.elapsed().as_millis();
-
-                if status.is_success() {
-                    info!(
-                        "Method: {}, Path: {}, Status: {}, Elapsed Time: {}ms",
-                        method, path, status, elapsed
-                    );
-                } else {
-                    log::error!(
-                        "Method: {}, Path: {}, Status: {}, Elapsed Time: {}ms",
-                        method,
-                        path,
-                        status,
-                        elapsed,
-                    );
-                }
-            }));
-            let feed_server = warp::serve(routes);
-            let firehose_listener = tokio::spawn(async move {
-                let jetstream = JetstreamConnector::new(JetstreamConfig {
-                    endpoint: DefaultJetstreamEndpoints::USEastOne.into(),
-                    wanted_collections: vec![
-                        Nsid::new("app.bsky.feed.post".to_string()).unwrap(),
-                        Nsid::new("app.bsky.feed.like".to_string()).unwrap(),
-                    ],
-                    compression: JetstreamCompression::Zstd,
-                    ..Default::default()
+            let send_interactions = send_interactions_route(auth, handler.clone());
+
+            let health = warp::path("health")
+                .and(warp::path::end())
+                .and(warp::get())
+                .map(|| StatusCode::OK);
+
+            let firehose_health = FirehoseHealth::new();
+            let ready_handler = handler.clone();
+            let ready_health = firehose_health.clone();
+            let firehose_stall_timeout = config.firehose_stall_timeout;
+            let ready = warp::path("ready")
+                .and(warp::path::end())
+                .and(warp::get())
+                .and_then(move || {
+                    ready(
+                        ready_health.clone(),
+                        firehose_stall_timeout,
+                        ready_handler.clone(),
+                    )
+                });
+
+            let api = did_json
+                .or(describe_feed_generator)
+                .or(get_feed_skeleton)
+                .or(send_interactions)
+                .or(health)
+                .or(ready);
+            #[cfg(feature = "metrics")]
+            let api = api.or(warp::path("metrics")
+                .and(warp::path::end())
+                .and(warp::get())
+                .map(crate::metrics::render));
+
+            info!(%address, "Serving feed");
+
+            let routes = api
+                .with(warp::log::custom(|info| {
+                    let status = info.status().as_u16();
+                    let latency_ms = info.elapsed().as_millis() as u64;
+                    if info.status().is_success() {
+                        info!(status, latency_ms, "Request served");
+                    } else {
+                        error!(status, latency_ms, "Request failed");
+                    }
+                }))
+                .with(warp::trace(|info| {
+                    info_span!(
+                        "request",
+                        method = %info.method(),
+                        path = info.path(),
+                        feed = field::Empty,
+                    )
+                }));
+            let (shutdown_sender, shutdown) = watch::channel(false);
+            tokio::spawn(async move {
+                shutdown_signal.await;
+                info!("Shutting down");
+                shutdown_sender.send_replace(true);
+            });
+            // The server keeps serving until the firehose listener has finished shutting down
+            let (firehose_stopped_sender, firehose_stopped) = oneshot::channel::<()>();
+            let (_, feed_server) =
+                warp::serve(routes).bind_with_graceful_shutdown(address, async {
+                    firehose_stopped.await.ok();
+                });
+            assert!(
+                !config.jetstream_endpoints.is_empty(),
+                "Config must contain at least one Jetstream endpoint"
+            );
+            let wanted_collections: Vec<Nsid> = config
+                .wanted_collections
+                .iter()
+                .map(|collection| {
+                    Nsid::new(collection.clone())
+                        .unwrap_or_else(|e| panic!("Invalid wanted collection {collection}: {e}"))
                 })
-                .unwrap();
-                let receiver = jetstream.connect().await.unwrap();
-                while let Ok(event) = receiver.recv_async().await {
-                    if let Commit(commit) = event {
-                        #[allow(clippy::collapsible_match)]
-                        match commit {
-                            CommitEvent::Create {
-                                info,
-                                commit:
-                                    CommitData {
-                                        info:
-                                            CommitInfo {
-                                                operation: CommitType::Create,
-                                                collection,
-                                                rkey,
-                                                ..
-                                            },
-                                        cid,
-                                        record: KnownRecord::AppBskyFeedPost(record),
-                                    },
-                            } => {
-                                #[allow(clippy::to_string_in_format_args)]
-                                let uri = format!(
-                                    "at://{}/{}/{}",
-                                    info.did.to_string(),
-                                    collection.to_string(),
-                                    rkey
-                                );
-
-                                let Some(time) =
-                                    DateTime::from_timestamp_micros(info.time_us as i64)
-                                else {
-                                    let time_us = info.time_us;
-                                    error!("Invalid post timestamp: {time_us}");
-                                    continue;
-                                };
-                                let post = Post {
-                                    author_did: Did(info.did.to_string()),
-                                    cid: Cid(serde_json::to_string(&cid).unwrap()),
-                                    uri: Uri(uri),
-                                    text: record.text.clone(),
-                                    labels: record
-                                        .labels
-                                        .as_ref()
-                                        .and_then(Label::from_atrium)
-                                        .unwrap_or_default(),
-                                    timestamp: time,
-                                    embed: record.embed.as_ref().and_then(Embed::from_atrium),
-                                    langs: record
-                                        .langs
-                                        .iter()
-                                        .filter_map(|lang| serde_json::to_string(&lang).ok())
-                                        .collect(),
-                                };
-                                handler.insert_post(post).await;
-                            }
-                            CommitEvent::Create {
-                                info,
-                                commit:
-                                    CommitData {
-                                        info:
-                                            CommitInfo {
-                                                operation: CommitType::Create,
-                                                collection,
-                                                rkey,
-                                                ..
-                                            },
-                                        record: KnownRecord::AppBskyFeedLike(record),
-                                        ..
-                                    },
-                            } => {
-                                #[allow(clippy::to_string_in_format_args)]
-                                let uri = format!(
-                                    "at://{}/{}/{}",
-                                    info.did.to_string(),
-                                    collection.to_string(),
-                                    rkey
-                                );
-                                handler
-                                    .like_post(Uri(uri), Uri(record.subject.uri.clone()))
-                                    .await;
-                            }
-                            CommitEvent::Delete {
-                                info,
-                                commit:
-                                    CommitInfo {
-                                        rkey, collection, ..
-                                    },
-                            } => {
-                                #[allow(clippy::to_string_in_format_args)]
-                                let uri = format!(
-                                    "at://{}/{}/{}",
-                                    info.did.to_string(),
-                                    collection.to_string(),
-                                    rkey
-                                );
-                                if collection.to_string() == "app.bsky.feed.post" {
-                                    handler.delete_post(Uri(uri)).await;
-                                } else if collection.to_string() == "app.bsky.feed.like" {
-                                    handler.delete_like(Uri(uri)).await;
+                .collect();
+            let wanted_dids: Vec<JetstreamDid> = config
+                .wanted_dids
+                .iter()
+                .map(|did| {
+                    JetstreamDid::new(did.clone())
+                        .unwrap_or_else(|e| panic!("Invalid wanted DID {did}: {e}"))
+                })
+                .collect();
+            let firehose_listener = tokio::spawn(
+                run_firehose(
+                    handler,
+                    config,
+                    wanted_collections,
+                    wanted_dids,
+                    firehose_health,
+                    shutdown,
+                    firehose_stopped_sender,
+                )
+                .instrument(info_span!("firehose")),
+            );
+
+            tokio::join!(feed_server, firehose_listener)
+                .1
+                .expect("Couldn't await tasks");
+        }
+    }
+}
+
+/// Forwards firehose events to the handler until shutdown, reconnecting & saving the cursor along the way.
+async fn run_firehose<Handler: FeedHandler + Send + Sync>(
+    mut handler: FeedRouter<Handler>,
+    config: Config,
+    wanted_collections: Vec<Nsid>,
+    wanted_dids: Vec<JetstreamDid>,
+    firehose_health: FirehoseHealth,
+    mut shutdown: watch::Receiver<bool>,
+    firehose_stopped: oneshot::Sender<()>,
+) {
+    let cursor_store = config.cursor_store.clone();
+    let cursor_rewind = config.cursor_rewind;
+    let mut last_time_us = cursor_store.as_ref().and_then(|store| store.load());
+    let mut endpoints = config.jetstream_endpoints.iter().cycle();
+    let mut failed_attempts = 0;
+    'connection: loop {
+        let endpoint = endpoints.next().unwrap();
+        let cursor = last_time_us
+            .map(|time_us| time_us.saturating_sub(cursor_rewind.as_micros() as u64))
+            .and_then(|time_us| DateTime::from_timestamp_micros(time_us as i64));
+        match cursor {
+            Some(cursor) => info!(endpoint, %cursor, "Connecting to firehose"),
+            None => info!(endpoint, "Connecting to firehose"),
+        }
+        let jetstream = JetstreamConnector::new(JetstreamConfig {
+            endpoint: endpoint.clone(),
+            wanted_collections: wanted_collections.clone(),
+            wanted_dids: wanted_dids.clone(),
+            compression: if config.jetstream_compression {
+                JetstreamCompression::Zstd
+            } else {
+                JetstreamCompression::None
+            },
+            cursor,
+            // Reconnection is handled here so the cursor & endpoint can change between attempts
+            max_retries: 1,
+            ..Default::default()
+        })
+        .expect("Invalid firehose configuration");
+        let mut received_events = false;
+        match jetstream.connect().await {
+            Ok(receiver) => {
+                let mut last_cursor_save = Instant::now();
+                loop {
+                    let event = tokio::select! {
+                        event = receiver.recv_async() => event,
+                        _ = shutdown.changed() => {
+                            // Finish handling events that were already received
+                            for event in receiver.drain() {
+                                if let Some(time_us) = handle_event(&mut handler, event).await {
+                                    last_time_us = Some(time_us);
                                 }
                             }
-                            _ => (),
+                            break 'connection;
+                        }
+                    };
+                    let Ok(event) = event else {
+                        break;
+                    };
+                    firehose_health.record_activity();
+                    if !received_events {
+                        info!(endpoint, "Firehose connected");
+                        received_events = true;
+                    }
+                    let Some(time_us) = handle_event(&mut handler, event).await else {
+                        continue;
+                    };
+                    last_time_us = Some(time_us);
+                    if let Some(store) = &cursor_store {
+                        if last_cursor_save.elapsed() >= CURSOR_SAVE_INTERVAL {
+                            store.save(time_us);
+                            last_cursor_save = Instant::now();
                         }
                     }
                 }
-            });
+                warn!(endpoint, "Firehose disconnected");
+            }
+            Err(e) => error!(endpoint, error = %e, "Firehose connection failed"),
+        }
+        if let (Some(store), Some(time_us)) = (&cursor_store, last_time_us) {
+            store.save(time_us);
+        }
+        failed_attempts = if received_events {
+            0
+        } else {
+            failed_attempts + 1
+        };
+        let delay = reconnect_delay(failed_attempts);
+        warn!(
+            delay_ms = delay.as_millis() as u64,
+            "Reconnecting to firehose"
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => (),
+            _ = shutdown.changed() => break,
+        }
+    }
+    handler.shutdown().await;
+    if let (Some(store), Some(time_us)) = (&cursor_store, last_time_us) {
+        store.save(time_us);
+    }
+    info!("Firehose stopped");
+    firehose_stopped.send(()).ok();
+}
 
-            tokio::join!(feed_server.run(address), firehose_listener)
-                .1
-                .expect("Couldn't await tasks");
+/// Completes when the process receives Ctrl+C, or SIGTERM on unix.
+async fn termination_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = tokio::signal::unix::signal(SignalKind::terminate())
+            .expect("Couldn't listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => (),
+            _ = terminate.recv() => (),
         }
     }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Couldn't listen for Ctrl+C");
+}
+
+/// Forwards a firehose event to the handler, returning the event's cursor if it was handled.
+async fn handle_event<Handler: FeedHandler>(
+    handler: &mut Handler,
+    event: JetstreamEvent,
+) -> Option<u64> {
+    let Commit(commit) = event else {
+        return None;
+    };
+    let (info, commit_info) = match &commit {
+        CommitEvent::Create { info, commit } | CommitEvent::Update { info, commit } => {
+            (info, &commit.info)
+        }
+        CommitEvent::Delete { info, commit } => (info, commit),
+    };
+    let time_us = info.time_us;
+    let span = debug_span!(
+        "commit",
+        did = info.did.as_str(),
+        collection = commit_info.collection.as_str(),
+        operation = ?commit_info.operation,
+        rkey = commit_info.rkey,
+        time_us,
+    );
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_commit(
+        // Only collections with handler callbacks get their own label to bound the number of series
+        match commit_info.collection.as_str() {
+            collection @ ("app.bsky.feed.post"
+            | "app.bsky.feed.like"
+            | "app.bsky.feed.repost"
+            | "app.bsky.graph.follow"
+            | "app.bsky.graph.block") => collection,
+            _ => "other",
+        },
+        match commit_info.operation {
+            CommitType::Create => "create",
+            CommitType::Update => "update",
+            CommitType::Delete => "delete",
+        },
+        time_us,
+    );
+    handle_commit(handler, commit).instrument(span).await;
+    Some(time_us)
+}
+
+/// The URI of a firehose record, `None` if the record key is invalid
+fn record_uri(did: &JetstreamDid, collection: &Nsid, rkey: &str) -> Option<Uri> {
+    match AtUri::record(&Did(did.to_string()), collection.as_str(), rkey) {
+        Ok(uri) => Some(uri.into()),
+        Err(e) => {
+            warn!(error = %e, "Skipping firehose record");
+            None
+        }
+    }
+}
+
+async fn handle_commit<Handler: FeedHandler>(handler: &mut Handler, commit: CommitEvent) {
+    #[allow(clippy::collapsible_match)]
+    match commit {
+        CommitEvent::Create {
+            info,
+            commit:
+                CommitData {
+                    info:
+                        CommitInfo {
+                            operation: CommitType::Create,
+                            collection,
+                            rkey,
+                            ..
+                        },
+                    cid,
+                    record: KnownRecord::AppBskyFeedPost(record),
+                },
+        } => {
+            let Some(uri) = record_uri(&info.did, &collection, &rkey) else {
+                return;
+            };
+
+            let Some(time) = DateTime::from_timestamp_micros(info.time_us as i64) else {
+                error!(time_us = info.time_us, "Invalid post timestamp");
+                return;
+            };
+            let post = Post {
+                author_did: Did(info.did.to_string()),
+                cid: Cid::from_atrium(&cid),
+                uri,
+                text: record.text.clone(),
+                labels: record
+                    .labels
+                    .as_ref()
+                    .and_then(Label::from_atrium)
+                    .unwrap_or_default(),
+                timestamp: time,
+                created_at: record.created_at.as_ref().with_timezone(&Utc),
+                embed: record.embed.as_ref().and_then(Embed::from_atrium),
+                langs: record
+                    .langs
+                    .iter()
+                    .flatten()
+                    .filter_map(|lang| lang.as_ref().as_str().parse().ok())
+                    .collect(),
+                reply: record.reply.as_ref().map(Reply::from_atrium),
+                facets: record
+                    .facets
+                    .iter()
+                    .flatten()
+                    .map(Facet::from_atrium)
+                    .collect(),
+            };
+            handler.insert_post(post).await;
+        }
+        CommitEvent::Create {
+            info,
+            commit:
+                CommitData {
+                    info:
+                        CommitInfo {
+                            operation: CommitType::Create,
+                            collection,
+                            rkey,
+                            ..
+                        },
+                    record: KnownRecord::AppBskyFeedLike(record),
+                    ..
+                },
+        } => {
+            let Some(uri) = record_uri(&info.did, &collection, &rkey) else {
+                return;
+            };
+            handler
+                .like_post(uri, Uri(record.subject.uri.clone()))
+                .await;
+        }
+        CommitEvent::Create {
+            info,
+            commit:
+                CommitData {
+                    info:
+                        CommitInfo {
+                            operation: CommitType::Create,
+                            collection,
+                            rkey,
+                            ..
+                        },
+                    record: KnownRecord::AppBskyFeedRepost(record),
+                    ..
+                },
+        } => {
+            let Some(uri) = record_uri(&info.did, &collection, &rkey) else {
+                return;
+            };
+            handler
+                .repost_post(uri, Uri(record.subject.uri.clone()))
+                .await;
+        }
+        CommitEvent::Create {
+            info,
+            commit:
+                CommitData {
+                    info:
+                        CommitInfo {
+                            operation: CommitType::Create,
+                            collection,
+                            rkey,
+                            ..
+                        },
+                    record: KnownRecord::AppBskyGraphFollow(record),
+                    ..
+                },
+        } => {
+            let Some(uri) = record_uri(&info.did, &collection, &rkey) else {
+                return;
+            };
+            handler
+                .follow(
+                    uri,
+                    Did(info.did.to_string()),
+                    Did(record.subject.to_string()),
+                )
+                .await;
+        }
+        CommitEvent::Create {
+            info,
+            commit:
+                CommitData {
+                    info:
+                        CommitInfo {
+                            operation: CommitType::Create,
+                            collection,
+                            rkey,
+                            ..
+                        },
+                    record: KnownRecord::AppBskyGraphBlock(record),
+                    ..
+                },
+        } => {
+            let Some(uri) = record_uri(&info.did, &collection, &rkey) else {
+                return;
+            };
+            handler
+                .block(
+                    uri,
+                    Did(info.did.to_string()),
+                    Did(record.subject.to_string()),
+                )
+                .await;
+        }
+        CommitEvent::Delete {
+            info,
+            commit: CommitInfo {
+                rkey, collection, ..
+            },
+        } => {
+            let Some(uri) = record_uri(&info.did, &collection, &rkey) else {
+                return;
+            };
+            if collection.to_string() == "app.bsky.feed.post" {
+                handler.delete_post(uri).await;
+            } else if collection.to_string() == "app.bsky.feed.like" {
+                handler.delete_like(uri).await;
+            } else if collection.to_string() == "app.bsky.feed.repost" {
+                handler.delete_repost(uri).await;
+            } else if collection.to_string() == "app.bsky.graph.follow" {
+                handler.unfollow(uri).await;
+            } else if collection.to_string() == "app.bsky.graph.block" {
+                handler.unblock(uri).await;
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Exponential backoff with jitter for firehose reconnection attempts.
+///
+/// The jitter keeps many feed generators from reconnecting in lockstep after a shared outage.
+fn reconnect_delay(failed_attempts: u32) -> Duration {
+    let delay = RECONNECT_BASE_DELAY
+        .saturating_mul(2_u32.saturating_pow(failed_attempts))
+        .min(RECONNECT_MAX_DELAY);
+    delay / 2 + delay.mul_f64(rand::random::<f64>() / 2.0)
 }
 
 async fn did_json(config: Config) -> Result<impl warp::Reply, warp::Rejection> {
@@ -252,12 +657,13 @@ async fn did_json(config: Config) -> Result<impl warp::Reply, warp::Rejection> {
             type_: "BskyFeedGenerator".to_owned(),
             service_endpoint: format!("https://{}", config.feed_generator_hostname),
         }],
+        verification_method: vec![],
     }))
 }
 
 async fn describe_feed_generator(
     config: Config,
-    feed_name: String,
+    feed_uris: Vec<String>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     Ok(warp::reply::json(&FeedGeneratorDescription {
         did: atrium_api::types::string::Did::new(format!(
@@ -265,39 +671,448 @@ async fn describe_feed_generator(
             config.feed_generator_hostname
         ))
         .unwrap(),
-        feeds: vec![Object::from(FeedData {
-            uri: format!(
-                "at://{}/app.bsky.feed.generator/{}",
-                config.publisher_did, feed_name
-            ),
-        })],
+        feeds: feed_uris
+            .into_iter()
+            .map(|uri| Object::from(FeedData { uri }))
+            .collect(),
         links: None,
     }))
 }
 
-async fn get_feed_skeleton<Handler: FeedHandler>(
+async fn get_feed_skeleton<Handler: FeedHandler + Send + Sync, Resolver: DidResolver>(
     query: FeedSkeletonQuery,
-    handler: Handler,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    let skeleton = handler
+    authorization: Option<String>,
+    auth: ServiceAuth<Resolver>,
+    handler: FeedRouter<Handler>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    // Feed URIs end in the feed name
+    let feed_name = query.feed.rsplit('/').next().unwrap_or_default().to_owned();
+    Span::current().record("feed", feed_name.as_str());
+    #[cfg(feature = "metrics")]
+    let (started, feed_name) = (
+        Instant::now(),
+        // Only registered feeds get their own label to bound the number of series
+        if handler.feed_uris().any(|uri| uri == query.feed) {
+            feed_name
+        } else {
+            "unknown".to_owned()
+        },
+    );
+    let response = feed_skeleton_response(query, authorization, auth, handler).await;
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_feed_skeleton(&feed_name, response.status(), started.elapsed());
+    Ok(response)
+}
+
+async fn feed_skeleton_response<Handler: FeedHandler + Send + Sync, Resolver: DidResolver>(
+    query: FeedSkeletonQuery,
+    authorization: Option<String>,
+    auth: ServiceAuth<Resolver>,
+    handler: FeedRouter<Handler>,
+) -> warp::reply::Response {
+    let lxm = "app.bsky.feed.getFeedSkeleton";
+    let viewer = match authorization {
+        Some(authorization) => match auth.verify(&authorization, lxm).await {
+            Ok(viewer) => Some(viewer),
+            // An unreachable DID host shouldn't take the feed down, so serve it without personalization
+            Err(e @ AuthError::Resolution(_)) => {
+                warn!(lxm, error = %e, "Serving feed without a viewer");
+                None
+            }
+            Err(e) => return unauthorized(lxm, e),
+        },
+        None => None,
+    };
+    let skeleton = match handler
         .serve_feed(Request {
             cursor: query.cursor.clone(),
             feed: query.feed.clone(),
             limit: query.limit.map(u8::from),
+            viewer,
         })
-        .await;
-    Ok::<warp::reply::Json, warp::Rejection>(warp::reply::json(&FeedSkeleton {
+        .await
+    {
+        Ok(skeleton) => skeleton,
+        Err(e) => return feed_error_reply(e),
+    };
+    warp::reply::json(&FeedSkeleton {
         cursor: skeleton.cursor,
         feed: skeleton
             .feed
             .into_iter()
-            .map(|uri| {
-                Object::from(atrium_api::app::bsky::feed::defs::SkeletonFeedPostData {
-                    feed_context: None,
-                    post: uri.0,
-                    reason: None,
+            .map(|item| {
+                Object::from(SkeletonFeedPostData {
+                    feed_context: item.feed_context,
+                    post: item.post.0,
+                    reason: item.repost.map(|repost| {
+                        Union::Refs(SkeletonFeedPostReasonRefs::SkeletonReasonRepost(Box::new(
+                            Object::from(SkeletonReasonRepostData { repost: repost.0 }),
+                        )))
+                    }),
                 })
             })
             .collect(),
-    }))
+    })
+    .into_response()
+}
+
+fn send_interactions_route<
+    Handler: FeedHandler + Clone + Send + Sync + 'static,
+    Resolver: DidResolver + Clone + Send + Sync + 'static,
+>(
+    auth: ServiceAuth<Resolver>,
+    handler: FeedRouter<Handler>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    warp::path("xrpc")
+        .and(warp::path("app.bsky.feed.sendInteractions"))
+        .and(warp::post())
+        .and(warp::body::content_length_limit(
+            MAX_INTERACTIONS_BODY_BYTES,
+        ))
+        .and(warp::body::json::<SendInteractionsInput>())
+        .and(warp::header::optional::<String>("authorization"))
+        .and_then(
+            move |input: SendInteractionsInput, authorization: Option<String>| {
+                send_interactions(input, authorization, auth.clone(), handler.clone())
+            },
+        )
+}
+
+async fn send_interactions<Handler: FeedHandler + Send + Sync, Resolver: DidResolver>(
+    input: SendInteractionsInput,
+    authorization: Option<String>,
+    auth: ServiceAuth<Resolver>,
+    handler: FeedRouter<Handler>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let Some(authorization) = authorization else {
+        return Ok(xrpc_error(
+            StatusCode::UNAUTHORIZED,
+            "AuthenticationRequired",
+            "Authentication required".to_owned(),
+        ));
+    };
+    let lxm = "app.bsky.feed.sendInteractions";
+    let viewer = match auth.verify(&authorization, lxm).await {
+        Ok(viewer) => viewer,
+        Err(e) => return Ok(unauthorized(lxm, e)),
+    };
+    handler
+        .on_interactions(
+            viewer,
+            input
+                .data
+                .interactions
+                .into_iter()
+                .map(|interaction| Interaction::from_atrium(interaction.data))
+                .collect(),
+        )
+        .await;
+    Ok(warp::reply::json(&SendInteractionsOutput {}).into_response())
+}
+
+/// Responds with 503 Service Unavailable when the firehose is disconnected or stalled, or a handler isn't ready.
+async fn ready<Handler: FeedHandler + Send + Sync>(
+    firehose_health: FirehoseHealth,
+    firehose_stall_timeout: Duration,
+    handler: FeedRouter<Handler>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let reply = if firehose_health.is_stalled(firehose_stall_timeout) {
+        warp::reply::with_status(
+            "Firehose disconnected or stalled",
+            StatusCode::SERVICE_UNAVAILABLE,
+        )
+    } else if !handler.ready().await {
+        warp::reply::with_status("Handler not ready", StatusCode::SERVICE_UNAVAILABLE)
+    } else {
+        warp::reply::with_status("OK", StatusCode::OK)
+    };
+    Ok(reply.into_response())
+}
+
+/// Responds with an XRPC error to a request to `lxm` whose service auth token is invalid.
+fn unauthorized(lxm: &str, error: AuthError) -> warp::reply::Response {
+    warn!(lxm, error = %error, "Rejected request");
+    xrpc_error(
+        StatusCode::UNAUTHORIZED,
+        "AuthenticationRequired",
+        error.to_string(),
+    )
+}
+
+fn feed_error_reply(error: FeedError) -> warp::reply::Response {
+    match error {
+        FeedError::InvalidCursor => {
+            xrpc_error(StatusCode::BAD_REQUEST, "InvalidCursor", error.to_string())
+        }
+        FeedError::UnknownFeed => {
+            xrpc_error(StatusCode::BAD_REQUEST, "UnknownFeed", error.to_string())
+        }
+        FeedError::Internal(message) => {
+            error!(message, "Feed request failed");
+            xrpc_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "InternalServerError",
+                "Internal Server Error".to_owned(),
+            )
+        }
+        FeedError::Custom {
+            status,
+            error,
+            message,
+        } => xrpc_error(
+            // An error body must not go out with a success or redirect status
+            StatusCode::from_u16(status)
+                .ok()
+                .filter(|status| status.is_client_error() || status.is_server_error())
+                .unwrap_or(StatusCode::BAD_REQUEST),
+            &error,
+            message,
+        ),
+    }
+}
+
+fn xrpc_error(status: StatusCode, error: &str, message: String) -> warp::reply::Response {
+    warp::reply::with_status(
+        warp::reply::json(&XrpcError {
+            error: error.to_owned(),
+            message,
+        }),
+        status,
+    )
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::tests::{k256_resolver, k256_token, valid_claims, SERVICE_DID, VIEWER_DID};
+    use crate::models::InteractionEvent;
+    use crate::test_util::RecordingHandler;
+    use rand::rngs::OsRng;
+    use serde_json::json;
+
+    const FEED: &str = "at://did:plc:publisher/app.bsky.feed.generator/cats";
+
+    fn query() -> FeedSkeletonQuery {
+        FeedSkeletonParameters {
+            cursor: None,
+            feed: FEED.to_string(),
+            limit: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_a_cap() {
+        for failed_attempts in [0, 1, 3, 5, 6, 40, u32::MAX] {
+            let delay = RECONNECT_BASE_DELAY
+                .saturating_mul(2_u32.saturating_pow(failed_attempts))
+                .min(RECONNECT_MAX_DELAY);
+            for _ in 0..100 {
+                let jittered = reconnect_delay(failed_attempts);
+                assert!(
+                    jittered >= delay / 2 && jittered <= delay,
+                    "{failed_attempts}: {jittered:?}"
+                );
+            }
+        }
+        assert!(reconnect_delay(0) <= Duration::from_secs(1));
+        assert!(reconnect_delay(3) >= Duration::from_secs(4));
+        assert!(reconnect_delay(u32::MAX) >= RECONNECT_MAX_DELAY / 2);
+    }
+
+    async fn body_json(response: warp::reply::Response) -> serde_json::Value {
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn maps_feed_errors_to_xrpc_errors() {
+        let cases = [
+            (
+                FeedError::InvalidCursor,
+                StatusCode::BAD_REQUEST,
+                json!({ "error": "InvalidCursor", "message": "Invalid cursor" }),
+            ),
+            (
+                FeedError::UnknownFeed,
+                StatusCode::BAD_REQUEST,
+                json!({ "error": "UnknownFeed", "message": "Unknown feed" }),
+            ),
+            (
+                // Internal details aren't leaked to clients
+                FeedError::Internal("database is locked".to_string()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                json!({ "error": "InternalServerError", "message": "Internal Server Error" }),
+            ),
+            (
+                FeedError::Custom {
+                    status: 429,
+                    error: "RateLimitExceeded".to_string(),
+                    message: "Slow down".to_string(),
+                },
+                StatusCode::TOO_MANY_REQUESTS,
+                json!({ "error": "RateLimitExceeded", "message": "Slow down" }),
+            ),
+            (
+                FeedError::Custom {
+                    status: 200,
+                    error: "NotAnError".to_string(),
+                    message: "Success status".to_string(),
+                },
+                StatusCode::BAD_REQUEST,
+                json!({ "error": "NotAnError", "message": "Success status" }),
+            ),
+            (
+                FeedError::Custom {
+                    status: 1000,
+                    error: "Invalid".to_string(),
+                    message: "Not a status".to_string(),
+                },
+                StatusCode::BAD_REQUEST,
+                json!({ "error": "Invalid", "message": "Not a status" }),
+            ),
+        ];
+        for (error, status, body) in cases {
+            let response = feed_error_reply(error);
+            assert_eq!(response.status(), status);
+            assert_eq!(body_json(response).await, body);
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_readiness_of_firehose_and_handlers() {
+        let router =
+            |handler| FeedRouter::new("did:plc:publisher", vec![("cats".to_string(), handler)]);
+        let firehose_health = FirehoseHealth::new();
+        let ready_handler = RecordingHandler::default();
+        let not_ready_handler = RecordingHandler {
+            not_ready: true,
+            ..Default::default()
+        };
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let response = ready(
+            firehose_health.clone(),
+            Duration::ZERO,
+            router(ready_handler.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = ready(
+            firehose_health.clone(),
+            Duration::from_secs(3600),
+            router(not_ready_handler),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = ready(
+            firehose_health,
+            Duration::from_secs(3600),
+            router(ready_handler),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn serves_feed_without_viewer_when_issuer_cant_be_resolved() {
+        let key = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let auth = ServiceAuth::new(SERVICE_DID.to_string(), k256_resolver(&key));
+        let handler = RecordingHandler::default();
+        let router = FeedRouter::new(
+            "did:plc:publisher",
+            vec![("cats".to_string(), handler.clone())],
+        );
+
+        let mut claims = valid_claims();
+        claims["iss"] = json!("did:plc:unresolvable");
+        let response = feed_skeleton_response(
+            query(),
+            Some(k256_token(&key, claims)),
+            auth.clone(),
+            router.clone(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut claims = valid_claims();
+        claims["exp"] = json!(Utc::now().timestamp() - 60);
+        let response =
+            feed_skeleton_response(query(), Some(k256_token(&key, claims)), auth, router).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        assert_eq!(*handler.viewers.lock().unwrap(), vec![None]);
+    }
+
+    fn interactions_body(count: usize) -> serde_json::Value {
+        json!({
+            "interactions": vec![
+                json!({
+                    "item": "at://did:plc:alice/app.bsky.feed.post/3k2a",
+                    "event": "app.bsky.feed.defs#requestLess",
+                    "feedContext": "cats",
+                });
+                count
+            ]
+        })
+    }
+
+    #[tokio::test]
+    async fn passes_authenticated_interactions_to_handlers() {
+        let key = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let handler = RecordingHandler::default();
+        let route = send_interactions_route(
+            ServiceAuth::new(SERVICE_DID.to_string(), k256_resolver(&key)),
+            FeedRouter::new(
+                "did:plc:publisher",
+                vec![("cats".to_string(), handler.clone())],
+            ),
+        );
+        let mut claims = valid_claims();
+        claims["lxm"] = json!("app.bsky.feed.sendInteractions");
+        let request = || {
+            warp::test::request()
+                .method("POST")
+                .path("/xrpc/app.bsky.feed.sendInteractions")
+        };
+
+        let response = request().json(&interactions_body(1)).reply(&route).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = request()
+            .header("authorization", k256_token(&key, claims.clone()))
+            .json(&interactions_body(1))
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Bodies over 256KB are rejected before they're decoded
+        let response = request()
+            .header("authorization", k256_token(&key, claims))
+            .json(&interactions_body(5_000))
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let received = handler.interactions.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (viewer, interactions) = &received[0];
+        assert_eq!(viewer.0, VIEWER_DID);
+        assert_eq!(
+            interactions,
+            &vec![Interaction {
+                item: Some(Uri("at://did:plc:alice/app.bsky.feed.post/3k2a".to_string())),
+                event: Some(InteractionEvent::RequestLess),
+                feed_context: Some("cats".to_string()),
+            }]
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use crate::models::{FeedResult, Post, Request, Uri};
+use crate::models::{Did, FeedError, FeedResult, Interaction, Post, Request, Uri};
 
 /// A feed handler is responsible for
 /// - Storing and managing firehose input.
@@ -12,5 +12,72 @@ pub trait FeedHandler {
         liked_post_uri: Uri,
     ) -> impl std::future::Future<Output = ()> + Send;
     fn delete_like(&mut self, like_uri: Uri) -> impl std::future::Future<Output = ()> + Send;
-    fn serve_feed(&self, request: Request) -> impl std::future::Future<Output = FeedResult> + Send;
+    /// Called when a post is reposted. Does nothing by default.
+    fn repost_post(
+        &mut self,
+        _repost_uri: Uri,
+        _reposted_post_uri: Uri,
+    ) -> impl std::future::Future<Output = ()> + Send {
+        async {}
+    }
+    /// Called when a repost is deleted. Does nothing by default.
+    fn delete_repost(&mut self, _repost_uri: Uri) -> impl std::future::Future<Output = ()> + Send {
+        async {}
+    }
+    /// Called when `follower` follows `followed`. Does nothing by default.
+    fn follow(
+        &mut self,
+        _follow_uri: Uri,
+        _follower: Did,
+        _followed: Did,
+    ) -> impl std::future::Future<Output = ()> + Send {
+        async {}
+    }
+    /// Called when a follow is deleted. Does nothing by default.
+    fn unfollow(&mut self, _follow_uri: Uri) -> impl std::future::Future<Output = ()> + Send {
+        async {}
+    }
+    /// Called when `blocker` blocks `blocked`. Does nothing by default.
+    fn block(
+        &mut self,
+        _block_uri: Uri,
+        _blocker: Did,
+        _blocked: Did,
+    ) -> impl std::future::Future<Output = ()> + Send {
+        async {}
+    }
+    /// Called when a block is deleted. Does nothing by default.
+    fn unblock(&mut self, _block_uri: Uri) -> impl std::future::Future<Output = ()> + Send {
+        async {}
+    }
+    /// Called once when the feed shuts down, after the last firehose event was handled. Does nothing by default.
+    ///
+    /// Use this to flush any buffered writes.
+    fn shutdown(&mut self) -> impl std::future::Future<Output = ()> + Send {
+        async {}
+    }
+    /// Reports whether the handler is ready to serve feed requests, used by the `/ready` route. Always ready by default.
+    ///
+    /// Use this to report when a database is unreachable or still loading.
+    fn ready(&self) -> impl std::future::Future<Output = bool> + Send {
+        async { true }
+    }
+    /// Called when `viewer` sends feedback about items in a feed, such as "show more" or "show less". Does nothing by default.
+    ///
+    /// Clients only send interactions to feeds published with `accepts_interactions`, see the `publish` utility.
+    /// Interactions don't name the feed they're for. When serving several feeds, every handler receives all interactions, use `feed_context` to tell feeds apart.
+    fn on_interactions(
+        &self,
+        _viewer: Did,
+        _interactions: Vec<Interaction>,
+    ) -> impl std::future::Future<Output = ()> + Send {
+        async {}
+    }
+    /// Serves a page of the requested feed.
+    ///
+    /// Returning an error responds to the request with the matching XRPC error, see `FeedError`.
+    fn serve_feed(
+        &self,
+        request: Request,
+    ) -> impl std::future::Future<Output = Result<FeedResult, FeedError>> + Send;
 }
@@ -0,0 +1,223 @@
+use crate::feed_handler::FeedHandler;
+use crate::models::{Did, FeedError, FeedResult, Interaction, Post, Request, Uri};
+
+/// Routes feed requests to the handler registered for the requested feed URI & forwards firehose input to every handler.
+#[derive(Clone)]
+pub(crate) struct FeedRouter<Handler: FeedHandler> {
+    feeds: Vec<(String, Handler)>,
+}
+
+impl<Handler: FeedHandler> FeedRouter<Handler> {
+    pub(crate) fn new(publisher_did: &str, feeds: Vec<(String, Handler)>) -> Self {
+        FeedRouter {
+            feeds: feeds
+                .into_iter()
+                .map(|(name, handler)| (feed_uri(publisher_did, &name), handler))
+                .collect(),
+        }
+    }
+
+    /// The AT-URIs of all registered feeds
+    pub(crate) fn feed_uris(&self) -> impl Iterator<Item = &str> {
+        self.feeds.iter().map(|(uri, _)| uri.as_str())
+    }
+}
+
+fn feed_uri(publisher_did: &str, feed_name: &str) -> String {
+    format!("at://{publisher_did}/app.bsky.feed.generator/{feed_name}")
+}
+
+impl<Handler: FeedHandler + Send + Sync> FeedHandler for FeedRouter<Handler> {
+    async fn insert_post(&mut self, post: Post) {
+        for (_, handler) in self.feeds.iter_mut() {
+            handler.insert_post(post.clone()).await;
+        }
+    }
+
+    async fn delete_post(&mut self, uri: Uri) {
+        for (_, handler) in self.feeds.iter_mut() {
+            handler.delete_post(uri.clone()).await;
+        }
+    }
+
+    async fn like_post(&mut self, like_uri: Uri, liked_post_uri: Uri) {
+        for (_, handler) in self.feeds.iter_mut() {
+            handler
+                .like_post(like_uri.clone(), liked_post_uri.clone())
+                .await;
+        }
+    }
+
+    async fn delete_like(&mut self, like_uri: Uri) {
+        for (_, handler) in self.feeds.iter_mut() {
+            handler.delete_like(like_uri.clone()).await;
+        }
+    }
+
+    async fn repost_post(&mut self, repost_uri: Uri, reposted_post_uri: Uri) {
+        for (_, handler) in self.feeds.iter_mut() {
+            handler
+                .repost_post(repost_uri.clone(), reposted_post_uri.clone())
+                .await;
+        }
+    }
+
+    async fn delete_repost(&mut self, repost_uri: Uri) {
+        for (_, handler) in self.feeds.iter_mut() {
+            handler.delete_repost(repost_uri.clone()).await;
+        }
+    }
+
+    async fn follow(&mut self, follow_uri: Uri, follower: Did, followed: Did) {
+        for (_, handler) in self.feeds.iter_mut() {
+            handler
+                .follow(follow_uri.clone(), follower.clone(), followed.clone())
+                .await;
+        }
+    }
+
+    async fn unfollow(&mut self, follow_uri: Uri) {
+        for (_, handler) in self.feeds.iter_mut() {
+            handler.unfollow(follow_uri.clone()).await;
+        }
+    }
+
+    async fn block(&mut self, block_uri: Uri, blocker: Did, blocked: Did) {
+        for (_, handler) in self.feeds.iter_mut() {
+            handler
+                .block(block_uri.clone(), blocker.clone(), blocked.clone())
+                .await;
+        }
+    }
+
+    async fn unblock(&mut self, block_uri: Uri) {
+        for (_, handler) in self.feeds.iter_mut() {
+            handler.unblock(block_uri.clone()).await;
+        }
+    }
+
+    async fn shutdown(&mut self) {
+        for (_, handler) in self.feeds.iter_mut() {
+            handler.shutdown().await;
+        }
+    }
+
+    async fn on_interactions(&self, viewer: Did, interactions: Vec<Interaction>) {
+        for (_, handler) in self.feeds.iter() {
+            handler
+                .on_interactions(viewer.clone(), interactions.clone())
+                .await;
+        }
+    }
+
+    async fn ready(&self) -> bool {
+        for (_, handler) in self.feeds.iter() {
+            if !handler.ready().await {
+                return false;
+            }
+        }
+        true
+    }
+
+    async fn serve_feed(&self, request: Request) -> Result<FeedResult, FeedError> {
+        match self.feeds.iter().find(|(uri, _)| *uri == request.feed) {
+            Some((_, handler)) => handler.serve_feed(request).await,
+            None => Err(FeedError::UnknownFeed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SkeletonItem;
+    use std::sync::{Arc, Mutex};
+
+    /// Serves a feed of one post named after the handler & records the firehose input it receives
+    #[derive(Clone)]
+    struct NamedHandler {
+        name: &'static str,
+        deleted: Arc<Mutex<Vec<Uri>>>,
+    }
+
+    impl NamedHandler {
+        fn new(name: &'static str) -> Self {
+            NamedHandler {
+                name,
+                deleted: Arc::default(),
+            }
+        }
+    }
+
+    impl FeedHandler for NamedHandler {
+        async fn insert_post(&mut self, _post: Post) {}
+
+        async fn delete_post(&mut self, uri: Uri) {
+            self.deleted.lock().unwrap().push(uri);
+        }
+
+        async fn like_post(&mut self, _like_uri: Uri, _liked_post_uri: Uri) {}
+
+        async fn delete_like(&mut self, _like_uri: Uri) {}
+
+        async fn serve_feed(&self, _request: Request) -> Result<FeedResult, FeedError> {
+            Ok(FeedResult {
+                cursor: None,
+                feed: vec![SkeletonItem::from(Uri(self.name.to_string()))],
+            })
+        }
+    }
+
+    fn router(handlers: &[&NamedHandler]) -> FeedRouter<NamedHandler> {
+        FeedRouter::new(
+            "did:plc:publisher",
+            handlers
+                .iter()
+                .map(|handler| (handler.name.to_string(), (*handler).clone()))
+                .collect(),
+        )
+    }
+
+    fn request(feed: &str) -> Request {
+        Request {
+            cursor: None,
+            feed: feed.to_string(),
+            limit: None,
+            viewer: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_requests_by_feed_uri() {
+        let router = router(&[&NamedHandler::new("cats"), &NamedHandler::new("dogs")]);
+        let result = router
+            .serve_feed(request(
+                "at://did:plc:publisher/app.bsky.feed.generator/dogs",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(result.feed[0].post, Uri("dogs".to_string()));
+
+        for unknown in [
+            "at://did:plc:publisher/app.bsky.feed.generator/birds",
+            "at://did:plc:other/app.bsky.feed.generator/cats",
+        ] {
+            assert!(matches!(
+                router.serve_feed(request(unknown)).await,
+                Err(FeedError::UnknownFeed)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn forwards_firehose_input_to_every_handler() {
+        let cats = NamedHandler::new("cats");
+        let dogs = NamedHandler::new("dogs");
+        let mut router = router(&[&cats, &dogs]);
+        let uri = Uri("at://did:plc:alice/app.bsky.feed.post/3k2a".to_string());
+        router.delete_post(uri.clone()).await;
+        for handler in [cats, dogs] {
+            assert_eq!(*handler.deleted.lock().unwrap(), vec![uri.clone()]);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Tracks when the firehose listener last made progress, so readiness can fail when it's disconnected or stalled.
+#[derive(Debug, Clone)]
+pub(crate) struct FirehoseHealth {
+    last_activity: Arc<Mutex<Instant>>,
+}
+
+impl FirehoseHealth {
+    /// Starts out healthy, giving the firehose until the stall timeout to connect.
+    pub(crate) fn new() -> Self {
+        FirehoseHealth {
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Records that the firehose delivered an event.
+    pub(crate) fn record_activity(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+    }
+
+    /// Whether the firehose made no progress for longer than `timeout`
+    pub(crate) fn is_stalled(&self, timeout: Duration) -> bool {
+        self.last_activity.lock().unwrap().elapsed() > timeout
+    }
+}
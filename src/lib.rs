@@ -1,13 +1,35 @@
+mod auth;
 mod config;
+mod cursor;
+mod cursor_store;
 mod feed;
 mod feed_handler;
+mod feed_router;
+mod health;
+#[cfg(feature = "metrics")]
+mod metrics;
 mod models;
+mod post_filter;
 mod public_api_test;
+#[cfg(feature = "sqlite")]
+mod sqlite_store;
+#[cfg(test)]
+mod test_util;
 mod utility_models;
 
+pub use config::Config;
+pub use cursor::{CursorCodec, FeedPosition};
+pub use cursor_store::{CursorStore, FileCursorStore, MemoryCursorStore};
 pub use feed::Feed;
 pub use feed_handler::FeedHandler;
 pub use models::{
-    Cid, Did, Embed, ExternalEmbed, FeedResult, ImageEmbed, Label, MediaEmbed, Post, QuoteEmbed,
-    Request, Uri, VideoEmbed,
+    AspectRatio, AtUri, Caption, Cid, Did, Embed, ExternalEmbed, Facet, FacetFeature, FeedError,
+    FeedResult, ImageEmbed, Interaction, InteractionEvent, Label, LanguageTag, MediaEmbed,
+    ParseError, Post, PostRef, QuoteEmbed, Reply, Request, SkeletonItem, Uri, VideoEmbed,
 };
+pub use post_filter::{
+    And, Authors, EmbedKind, ExcludeLabels, FilteredHandler, HasEmbed, IsReply, Keywords, Language,
+    Not, Or, PostFilter, TextMatches, Unlabeled,
+};
+#[cfg(feature = "sqlite")]
+pub use sqlite_store::SqliteStore;
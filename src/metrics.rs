@@ -0,0 +1,104 @@
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use prometheus::{
+    Encoder, Gauge, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use tracing::error;
+use warp::http::StatusCode;
+use warp::Reply;
+
+/// Prometheus metrics for firehose ingestion & feed serving, exposed on `/metrics`
+struct Metrics {
+    registry: Registry,
+    commits: IntCounterVec,
+    firehose_lag: Gauge,
+    feed_skeleton_latency: HistogramVec,
+    feed_skeleton_responses: IntCounterVec,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(|| {
+    let registry = Registry::new();
+    let commits = IntCounterVec::new(
+        Opts::new(
+            "skyfeed_firehose_commits_total",
+            "Firehose commits handled, by collection & operation",
+        ),
+        &["collection", "operation"],
+    )
+    .unwrap();
+    let firehose_lag = Gauge::new(
+        "skyfeed_firehose_lag_seconds",
+        "Time between the most recently handled firehose event & when it was handled",
+    )
+    .unwrap();
+    let feed_skeleton_latency = HistogramVec::new(
+        HistogramOpts::new(
+            "skyfeed_feed_skeleton_duration_seconds",
+            "getFeedSkeleton response latency, by feed",
+        ),
+        &["feed"],
+    )
+    .unwrap();
+    let feed_skeleton_responses = IntCounterVec::new(
+        Opts::new(
+            "skyfeed_feed_skeleton_responses_total",
+            "getFeedSkeleton responses, by feed & status",
+        ),
+        &["feed", "status"],
+    )
+    .unwrap();
+    registry.register(Box::new(commits.clone())).unwrap();
+    registry.register(Box::new(firehose_lag.clone())).unwrap();
+    registry
+        .register(Box::new(feed_skeleton_latency.clone()))
+        .unwrap();
+    registry
+        .register(Box::new(feed_skeleton_responses.clone()))
+        .unwrap();
+    Metrics {
+        registry,
+        commits,
+        firehose_lag,
+        feed_skeleton_latency,
+        feed_skeleton_responses,
+    }
+});
+
+/// Records a handled firehose commit & the firehose lag at the time it was handled
+pub(crate) fn record_commit(collection: &str, operation: &str, time_us: u64) {
+    METRICS
+        .commits
+        .with_label_values(&[collection, operation])
+        .inc();
+    let now_us = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64;
+    METRICS
+        .firehose_lag
+        .set(Duration::from_micros(now_us.saturating_sub(time_us)).as_secs_f64());
+}
+
+/// Records a getFeedSkeleton response
+pub(crate) fn record_feed_skeleton(feed: &str, status: StatusCode, latency: Duration) {
+    METRICS
+        .feed_skeleton_latency
+        .with_label_values(&[feed])
+        .observe(latency.as_secs_f64());
+    METRICS
+        .feed_skeleton_responses
+        .with_label_values(&[feed, status.as_str()])
+        .inc();
+}
+
+/// Renders all metrics in the Prometheus text format
+pub(crate) fn render() -> warp::reply::Response {
+    let mut buffer = Vec::new();
+    let encoder = TextEncoder::new();
+    if let Err(e) = encoder.encode(&METRICS.registry.gather(), &mut buffer) {
+        error!(error = %e, "Failed to encode metrics");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    warp::reply::with_header(buffer, "content-type", encoder.format_type()).into_response()
+}
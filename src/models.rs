@@ -1,93 +1,499 @@
 use atrium_api::{
     app::bsky::{
         embed::record_with_media::MainMediaRefs,
-        feed::post::{RecordEmbedRefs, RecordLabelsRefs},
+        feed::post::{RecordEmbedRefs, RecordLabelsRefs, ReplyRef},
+        richtext::facet::MainFeaturesItem,
     },
-    types::{BlobRef, Object, TypedBlobRef, Union},
+    types::{BlobRef, Object, TypedBlobRef, Union, UnknownData},
 };
-use chrono::{DateTime, Utc};
-use log::trace;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Request {
     pub cursor: Option<String>,
     pub feed: String,
     pub limit: Option<u8>,
+    /// The DID of the account requesting the feed, verified from the request's service auth token.
+    ///
+    /// `None` when the request is unauthenticated.
+    pub viewer: Option<Did>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Post {
     pub author_did: Did,
     pub cid: Cid,
     pub uri: Uri,
     pub text: String,
     pub labels: Vec<Label>,
-    pub langs: Vec<String>,
+    pub langs: Vec<LanguageTag>,
+    /// When the firehose received this post, taken from Jetstream's `time_us`.
     pub timestamp: DateTime<Utc>,
+    /// The record's `createdAt`. Set by the author's client, so it can be backdated or in the future.
+    pub created_at: DateTime<Utc>,
     pub embed: Option<Embed>,
+    /// Present when this post is a reply to another post.
+    pub reply: Option<Reply>,
+    /// Rich text annotations (mentions, links & hashtags) of `text`.
+    pub facets: Vec<Facet>,
 }
 
-#[derive(Debug, Clone)]
-pub struct Cid(pub String);
+impl Post {
+    /// Returns true if this post is a reply to another post.
+    pub fn is_reply(&self) -> bool {
+        self.reply.is_some()
+    }
 
-#[derive(Debug, Clone)]
+    /// All hashtags in this post, without the leading `#`.
+    pub fn hashtags(&self) -> impl Iterator<Item = &str> {
+        self.facet_features().filter_map(|feature| match feature {
+            FacetFeature::Tag(tag) => Some(tag.as_str()),
+            _ => None,
+        })
+    }
+
+    /// All accounts mentioned in this post.
+    pub fn mentions(&self) -> impl Iterator<Item = &Did> {
+        self.facet_features().filter_map(|feature| match feature {
+            FacetFeature::Mention(did) => Some(did),
+            _ => None,
+        })
+    }
+
+    /// All links in this post.
+    pub fn links(&self) -> impl Iterator<Item = &str> {
+        self.facet_features().filter_map(|feature| match feature {
+            FacetFeature::Link(uri) => Some(uri.as_str()),
+            _ => None,
+        })
+    }
+
+    /// `created_at` clamped to no later than `timestamp`, so future-dated posts can't jump the queue of a feed sorted by time.
+    ///
+    /// Backdated posts keep their claimed time, which only sinks them in a newest-first feed.
+    pub fn effective_time(&self) -> DateTime<Utc> {
+        self.created_at.min(self.timestamp)
+    }
+
+    /// Returns true if `created_at` is more than `tolerance` after the firehose received the post.
+    pub fn is_future_dated(&self, tolerance: Duration) -> bool {
+        self.created_at - self.timestamp > TimeDelta::from_std(tolerance).unwrap_or(TimeDelta::MAX)
+    }
+
+    /// Returns true if `created_at` is more than `tolerance` before the firehose received the post.
+    pub fn is_backdated(&self, tolerance: Duration) -> bool {
+        self.timestamp - self.created_at > TimeDelta::from_std(tolerance).unwrap_or(TimeDelta::MAX)
+    }
+
+    fn facet_features(&self) -> impl Iterator<Item = &FacetFeature> {
+        self.facets.iter().flat_map(|facet| facet.features.iter())
+    }
+}
+
+/// An annotation of a range of a post's text.
+///
+/// `byte_start` (inclusive) & `byte_end` (exclusive) index the UTF-8 bytes of the post text.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Facet {
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub features: Vec<FacetFeature>,
+}
+
+impl Facet {
+    pub(crate) fn from_atrium(value: &atrium_api::app::bsky::richtext::facet::Main) -> Self {
+        Facet {
+            byte_start: value.index.byte_start,
+            byte_end: value.index.byte_end,
+            features: value
+                .features
+                .iter()
+                .filter_map(|feature| match feature {
+                    Union::Refs(MainFeaturesItem::Mention(mention)) => {
+                        Some(FacetFeature::Mention(Did(mention.did.to_string())))
+                    }
+                    Union::Refs(MainFeaturesItem::Link(link)) => {
+                        Some(FacetFeature::Link(link.uri.clone()))
+                    }
+                    Union::Refs(MainFeaturesItem::Tag(tag)) => {
+                        Some(FacetFeature::Tag(tag.tag.clone()))
+                    }
+                    Union::Unknown(_) => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// The annotated slice of `text`, or `None` if the range doesn't fall on valid character boundaries.
+    pub fn slice<'a>(&self, text: &'a str) -> Option<&'a str> {
+        text.get(self.byte_start..self.byte_end)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FacetFeature {
+    /// A mention of another account.
+    Mention(Did),
+    /// A link to a complete URL. The annotated text may be shortened.
+    Link(String),
+    /// A hashtag, without the leading `#`.
+    Tag(String),
+}
+
+/// The thread position of a reply.
+///
+/// - parent: The post being directly replied to.
+/// - root: The top-level post of the thread.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Reply {
+    pub parent: PostRef,
+    pub root: PostRef,
+}
+
+impl Reply {
+    pub(crate) fn from_atrium(value: &ReplyRef) -> Self {
+        Reply {
+            parent: PostRef {
+                uri: Uri(value.parent.uri.clone()),
+                cid: Cid::from_atrium(&value.parent.cid),
+            },
+            root: PostRef {
+                uri: Uri(value.root.uri.clone()),
+                cid: Cid::from_atrium(&value.root.cid),
+            },
+        }
+    }
+
+    /// Returns true if this post replies directly to the top-level post of the thread.
+    pub fn is_top_level(&self) -> bool {
+        self.parent.uri == self.root.uri
+    }
+}
+
+/// A reference to a specific version of a post.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PostRef {
+    pub uri: Uri,
+    pub cid: Cid,
+}
+
+/// A content identifier (CID): the hash of a record or blob.
+///
+/// Parses & displays as a base32 multibase string, like `bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cid(cid::Cid);
+
+impl Cid {
+    pub(crate) fn from_atrium(value: &atrium_api::types::string::Cid) -> Self {
+        Cid(*value.as_ref())
+    }
+}
+
+impl FromStr for Cid {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cid = cid::Cid::try_from(s).map_err(|e| ParseError::new("CID", e))?;
+        if cid.version() != cid::Version::V1 {
+            return Err(ParseError::new("CID", "Only CIDv1 is supported"));
+        }
+        Ok(Cid(cid))
+    }
+}
+
+impl TryFrom<String> for Cid {
+    type Error = ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Cid> for String {
+    fn from(value: Cid) -> Self {
+        value.to_string()
+    }
+}
+
+impl Display for Cid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A BCP-47 language tag, like `en` or `pt-BR`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LanguageTag(String);
+
+impl LanguageTag {
+    /// The language without any script or region subtags, `pt` for `pt-BR`.
+    pub fn primary_subtag(&self) -> &str {
+        self.0.split('-').next().unwrap_or_default()
+    }
+
+    /// Returns true if `tag` is this language or a more specific variant of it, ignoring case.
+    ///
+    /// `en` matches `en` & `en-US`, but `en-US` doesn't match `en`.
+    pub fn matches(&self, tag: &LanguageTag) -> bool {
+        tag.0.len() >= self.0.len()
+            && tag.0[..self.0.len()].eq_ignore_ascii_case(&self.0)
+            && (tag.0.len() == self.0.len() || tag.0.as_bytes()[self.0.len()] == b'-')
+    }
+}
+
+impl FromStr for LanguageTag {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut subtags = s.split('-');
+        let primary = subtags.next().unwrap_or_default();
+        let valid_primary = matches!(primary, "i" | "x" | "I" | "X")
+            || ((2..=8).contains(&primary.len())
+                && primary.chars().all(|c| c.is_ascii_alphabetic()));
+        let valid_subtags = subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+        if !valid_primary || !valid_subtags {
+            return Err(ParseError::new("language tag", s));
+        }
+        Ok(LanguageTag(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for LanguageTag {
+    type Error = ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<LanguageTag> for String {
+    fn from(value: LanguageTag) -> Self {
+        value.0
+    }
+}
+
+impl AsRef<str> for LanguageTag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for LanguageTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Returned when parsing an identifier from a string fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    kind: &'static str,
+    message: String,
+}
+
+impl ParseError {
+    fn new(kind: &'static str, message: impl Display) -> Self {
+        ParseError {
+            kind,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid {}: {}", self.kind, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Did(pub String);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Embed {
     Images(Vec<ImageEmbed>),
     Video(VideoEmbed),
     External(ExternalEmbed),
     Quote(QuoteEmbed),
     QuoteWithMedia(QuoteEmbed, MediaEmbed),
+    /// An embed type this version of skyfeed doesn't know, like one from a newer lexicon.
+    Unknown {
+        /// The embed's `$type`, like `app.bsky.embed.video`
+        r#type: String,
+        /// The whole embed as JSON, including `$type`
+        raw_json: String,
+    },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MediaEmbed {
     Images(Vec<ImageEmbed>),
     Video(VideoEmbed),
     External(ExternalEmbed),
+    /// A media type this version of skyfeed doesn't know, see `Embed::Unknown`
+    Unknown {
+        r#type: String,
+        raw_json: String,
+    },
 }
 
-#[derive(Debug, Clone)]
+const IMAGE_CDN: &str = "https://cdn.bsky.app/img";
+const VIDEO_CDN: &str = "https://video.bsky.app/watch";
+
+/// The width & height of an image or video, as declared by the author's client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AspectRatio {
+    pub width: u64,
+    pub height: u64,
+}
+
+impl AspectRatio {
+    /// Width divided by height
+    pub fn ratio(&self) -> f64 {
+        self.width as f64 / self.height as f64
+    }
+
+    fn from_atrium(value: &atrium_api::app::bsky::embed::defs::AspectRatio) -> Self {
+        AspectRatio {
+            width: value.width.get(),
+            height: value.height.get(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ImageEmbed {
     pub cid: Cid,
     pub alt_text: String,
     pub mime_type: String,
+    /// Size of the image blob in bytes, 0 for legacy blob references that don't record it
+    pub size: u64,
+    pub aspect_ratio: Option<AspectRatio>,
 }
 
 impl ImageEmbed {
+    /// Returns true if the author described the image for screen readers.
+    pub fn has_alt_text(&self) -> bool {
+        !self.alt_text.trim().is_empty()
+    }
+
+    /// The Bluesky CDN URL of a thumbnail of this image. `author` is the DID of the post's author.
+    pub fn thumbnail_url(&self, author: &Did) -> String {
+        format!(
+            "{IMAGE_CDN}/feed_thumbnail/plain/{}/{}@jpeg",
+            author.0, self.cid
+        )
+    }
+
+    /// The Bluesky CDN URL of this image at full size. `author` is the DID of the post's author.
+    pub fn fullsize_url(&self, author: &Did) -> String {
+        format!(
+            "{IMAGE_CDN}/feed_fullsize/plain/{}/{}@jpeg",
+            author.0, self.cid
+        )
+    }
+
     fn from_atrium(value: Object<atrium_api::app::bsky::embed::images::ImageData>) -> Option<Self> {
-        let BlobRef::Typed(TypedBlobRef::Blob(blob)) = &value.image else {
-            return None;
-        };
+        let blob = BlobInfo::from_atrium(&value.image)?;
         Some(ImageEmbed {
-            cid: Cid(blob.r#ref.0.to_string()),
+            cid: blob.cid,
             alt_text: value.alt.clone(),
-            mime_type: blob.mime_type.clone(),
+            mime_type: blob.mime_type,
+            size: blob.size,
+            aspect_ratio: value.aspect_ratio.as_ref().map(AspectRatio::from_atrium),
         })
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct VideoEmbed {
     pub cid: Cid,
     pub alt_text: String,
+    pub mime_type: String,
+    /// Size of the video blob in bytes, 0 for legacy blob references that don't record it
+    pub size: u64,
+    pub aspect_ratio: Option<AspectRatio>,
+    pub captions: Vec<Caption>,
 }
 
 impl VideoEmbed {
+    /// The languages this video has captions in
+    pub fn caption_languages(&self) -> impl Iterator<Item = &LanguageTag> {
+        self.captions.iter().map(|caption| &caption.lang)
+    }
+
+    /// The Bluesky CDN URL of a thumbnail of this video. `author` is the DID of the post's author.
+    pub fn thumbnail_url(&self, author: &Did) -> String {
+        format!(
+            "{VIDEO_CDN}/{}/{}/thumbnail.jpg",
+            encode_did(author),
+            self.cid
+        )
+    }
+
+    /// The Bluesky CDN URL of this video's HLS playlist. `author` is the DID of the post's author.
+    pub fn playlist_url(&self, author: &Did) -> String {
+        format!(
+            "{VIDEO_CDN}/{}/{}/playlist.m3u8",
+            encode_did(author),
+            self.cid
+        )
+    }
+
     fn from_atrium(video: Object<atrium_api::app::bsky::embed::video::MainData>) -> Option<Self> {
-        let BlobRef::Typed(TypedBlobRef::Blob(blob)) = &video.video else {
-            return None;
-        };
+        let blob = BlobInfo::from_atrium(&video.video)?;
         Some(VideoEmbed {
-            cid: Cid(blob.r#ref.0.to_string()),
+            cid: blob.cid,
             alt_text: video.alt.clone().unwrap_or_default(),
+            mime_type: blob.mime_type,
+            size: blob.size,
+            aspect_ratio: video.aspect_ratio.as_ref().map(AspectRatio::from_atrium),
+            captions: video
+                .captions
+                .iter()
+                .flatten()
+                .filter_map(Caption::from_atrium)
+                .collect(),
         })
     }
 }
 
-#[derive(Debug, Clone)]
+/// A captions file of a video.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Caption {
+    pub cid: Cid,
+    pub lang: LanguageTag,
+    pub mime_type: String,
+    /// Size of the captions blob in bytes, 0 for legacy blob references that don't record it
+    pub size: u64,
+}
+
+impl Caption {
+    fn from_atrium(value: &atrium_api::app::bsky::embed::video::Caption) -> Option<Self> {
+        let blob = BlobInfo::from_atrium(&value.file)?;
+        Some(Caption {
+            cid: blob.cid,
+            lang: value.lang.as_ref().as_str().parse().ok()?,
+            mime_type: blob.mime_type,
+            size: blob.size,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ExternalEmbed {
     pub title: String,
     pub description: String,
@@ -96,22 +502,66 @@ pub struct ExternalEmbed {
 }
 
 impl ExternalEmbed {
+    /// The Bluesky CDN URL of the link card's thumbnail. `author` is the DID of the post's author.
+    pub fn thumbnail_url(&self, author: &Did) -> Option<String> {
+        self.thumbnail
+            .map(|cid| format!("{IMAGE_CDN}/feed_thumbnail/plain/{}/{cid}@jpeg", author.0))
+    }
+
     fn from_atrium(external: Object<atrium_api::app::bsky::embed::external::MainData>) -> Self {
         ExternalEmbed {
             title: external.external.title.clone(),
             description: external.external.description.clone(),
             uri: external.external.uri.clone(),
-            thumbnail: external.external.thumb.clone().and_then(|thumb| {
-                let BlobRef::Typed(TypedBlobRef::Blob(blob)) = &thumb else {
-                    return None;
-                };
-                Some(Cid(blob.r#ref.0.to_string()))
+            thumbnail: external
+                .external
+                .thumb
+                .as_ref()
+                .and_then(BlobInfo::from_atrium)
+                .map(|blob| blob.cid),
+        }
+    }
+}
+
+/// The video CDN expects the DID as a percent-encoded path segment
+fn encode_did(did: &Did) -> String {
+    did.0.replace(':', "%3A")
+}
+
+/// The metadata of a blob, from either a current or a legacy blob reference
+struct BlobInfo {
+    cid: Cid,
+    mime_type: String,
+    size: u64,
+}
+
+impl BlobInfo {
+    fn from_atrium(value: &BlobRef) -> Option<Self> {
+        match value {
+            BlobRef::Typed(TypedBlobRef::Blob(blob)) => Some(BlobInfo {
+                cid: Cid(blob.r#ref.0),
+                mime_type: blob.mime_type.clone(),
+                size: blob.size as u64,
+            }),
+            // Legacy blob references don't record the blob's size
+            BlobRef::Untyped(blob) => Some(BlobInfo {
+                cid: blob.cid.parse().ok()?,
+                mime_type: blob.mime_type.clone(),
+                size: 0,
             }),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// The `$type` & the JSON of a union member this version of skyfeed doesn't know
+fn unknown_parts(value: &UnknownData) -> (String, String) {
+    (
+        value.r#type.clone(),
+        serde_json::to_string(value).unwrap_or_default(),
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct QuoteEmbed {
     pub cid: Cid,
     pub uri: String,
@@ -130,12 +580,31 @@ impl Label {
                         .collect::<Vec<Label>>(),
                 ),
             },
-            Union::Unknown(_) => None,
+            // Newer label types are decoded if they list their values like self labels do
+            Union::Unknown(unknown) => {
+                let json = serde_json::to_value(&unknown.data).ok()?;
+                Some(
+                    json.get("values")?
+                        .as_array()?
+                        .iter()
+                        .filter_map(|label| label.get("val")?.as_str())
+                        .map(|val| Label::from(val.to_string()))
+                        .collect(),
+                )
+            }
         }
     }
 }
 
 impl Embed {
+    /// The embedded images, including those of a quote with images.
+    pub fn images(&self) -> &[ImageEmbed] {
+        match self {
+            Embed::Images(images) | Embed::QuoteWithMedia(_, MediaEmbed::Images(images)) => images,
+            _ => &[],
+        }
+    }
+
     pub(crate) fn from_atrium(value: &Union<RecordEmbedRefs>) -> Option<Self> {
         match value {
             Union::Refs(e) => match e {
@@ -153,55 +622,52 @@ impl Embed {
                 RecordEmbedRefs::AppBskyEmbedExternalMain(external) => Some(Embed::External(
                     ExternalEmbed::from_atrium(*external.clone()),
                 )),
-                RecordEmbedRefs::AppBskyEmbedRecordMain(quote) => {
-                    let Ok(cid) = serde_json::to_string(&quote.data.record.cid) else {
-                        trace!("Cid serialization failed");
-                        return None;
-                    };
-                    Some(Embed::Quote(QuoteEmbed {
-                        cid: Cid(cid),
-                        uri: quote.data.record.uri.clone(),
-                    }))
-                }
+                RecordEmbedRefs::AppBskyEmbedRecordMain(quote) => Some(Embed::Quote(QuoteEmbed {
+                    cid: Cid::from_atrium(&quote.data.record.cid),
+                    uri: quote.data.record.uri.clone(),
+                })),
                 RecordEmbedRefs::AppBskyEmbedRecordWithMediaMain(quote_with_media) => {
-                    let Union::Refs(media) = &quote_with_media.media else {
-                        return None;
-                    };
-                    let media = match media {
-                        MainMediaRefs::AppBskyEmbedImagesMain(object) => MediaEmbed::Images(
-                            object
-                                .images
-                                .clone()
-                                .into_iter()
-                                .filter_map(ImageEmbed::from_atrium)
-                                .collect(),
-                        ),
-                        MainMediaRefs::AppBskyEmbedVideoMain(object) => {
-                            MediaEmbed::Video(VideoEmbed::from_atrium(*object.clone())?)
-                        }
-                        MainMediaRefs::AppBskyEmbedExternalMain(object) => {
-                            MediaEmbed::External(ExternalEmbed::from_atrium(*object.clone()))
+                    let media = match &quote_with_media.media {
+                        Union::Unknown(unknown) => {
+                            let (r#type, raw_json) = unknown_parts(unknown);
+                            MediaEmbed::Unknown { r#type, raw_json }
                         }
-                    };
-                    let Ok(cid) = serde_json::to_string(&quote_with_media.record.record.cid) else {
-                        trace!("Cid serialization failed");
-                        return None;
+                        Union::Refs(media) => match media {
+                            MainMediaRefs::AppBskyEmbedImagesMain(object) => MediaEmbed::Images(
+                                object
+                                    .images
+                                    .clone()
+                                    .into_iter()
+                                    .filter_map(ImageEmbed::from_atrium)
+                                    .collect(),
+                            ),
+                            MainMediaRefs::AppBskyEmbedVideoMain(object) => {
+                                MediaEmbed::Video(VideoEmbed::from_atrium(*object.clone())?)
+                            }
+                            MainMediaRefs::AppBskyEmbedExternalMain(object) => {
+                                MediaEmbed::External(ExternalEmbed::from_atrium(*object.clone()))
+                            }
+                        },
                     };
                     Some(Embed::QuoteWithMedia(
                         QuoteEmbed {
-                            cid: Cid(cid),
+                            cid: Cid::from_atrium(&quote_with_media.record.record.cid),
                             uri: quote_with_media.record.record.uri.clone(),
                         },
                         media,
                     ))
                 }
             },
-            Union::Unknown(_) => None,
+            Union::Unknown(unknown) => {
+                let (r#type, raw_json) = unknown_parts(unknown);
+                Some(Embed::Unknown { r#type, raw_json })
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum Label {
     Hide,
     Warn,
@@ -228,11 +694,553 @@ impl From<String> for Label {
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+impl From<Label> for String {
+    fn from(value: Label) -> Self {
+        match value {
+            Label::Hide => "!hide".to_string(),
+            Label::Warn => "!warn".to_string(),
+            Label::NoUnauthenticated => "!no-unauthenticated".to_string(),
+            Label::Porn => "porn".to_string(),
+            Label::Sexual => "sexual".to_string(),
+            Label::GraphicMedia => "graphic-media".to_string(),
+            Label::Nudity => "nudity".to_string(),
+            Label::Other(other) => other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Uri(pub String);
 
-#[derive(Debug, Clone)]
+impl Uri {
+    /// Parses this URI as an `AtUri` to access its components.
+    pub fn parse(&self) -> Result<AtUri, ParseError> {
+        self.0.parse()
+    }
+}
+
+impl From<AtUri> for Uri {
+    fn from(value: AtUri) -> Self {
+        Uri(value.to_string())
+    }
+}
+
+/// A validated AT-URI, like `at://did:plc:ewvi7nxzyoun6zhxrhs64oiz/app.bsky.feed.post/3k2a4yq3ujf2e`.
+///
+/// The authority is a DID or handle, optionally followed by a collection NSID & a record key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AtUri {
+    authority: String,
+    collection: Option<String>,
+    rkey: Option<String>,
+}
+
+impl AtUri {
+    /// The URI of a record
+    pub fn record(
+        did: &Did,
+        collection: impl Into<String>,
+        rkey: impl Into<String>,
+    ) -> Result<Self, ParseError> {
+        let uri = AtUri {
+            authority: did.0.clone(),
+            collection: Some(collection.into()),
+            rkey: Some(rkey.into()),
+        };
+        uri.validate()?;
+        Ok(uri)
+    }
+
+    /// The DID or handle of the repository
+    pub fn authority(&self) -> &str {
+        &self.authority
+    }
+
+    /// The repository's DID, `None` when the authority is a handle
+    pub fn did(&self) -> Option<Did> {
+        self.authority
+            .starts_with("did:")
+            .then(|| Did(self.authority.clone()))
+    }
+
+    /// The NSID of the record's collection, like `app.bsky.feed.post`
+    pub fn collection(&self) -> Option<&str> {
+        self.collection.as_deref()
+    }
+
+    /// The record key
+    pub fn rkey(&self) -> Option<&str> {
+        self.rkey.as_deref()
+    }
+
+    /// Returns true if this is the URI of a post
+    pub fn is_post(&self) -> bool {
+        self.collection() == Some("app.bsky.feed.post") && self.rkey.is_some()
+    }
+
+    fn validate(&self) -> Result<(), ParseError> {
+        let error = |message: &str| Err(ParseError::new("AT-URI", message));
+        if !is_did(&self.authority) && !is_handle(&self.authority) {
+            return error("The authority must be a DID or handle");
+        }
+        if let Some(collection) = &self.collection {
+            if !is_nsid(collection) {
+                return error("The collection must be an NSID");
+            }
+        }
+        if let Some(rkey) = &self.rkey {
+            let valid_chars = rkey
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':' | '~'));
+            if !(1..=512).contains(&rkey.len()) || !valid_chars || rkey == "." || rkey == ".." {
+                return error("Invalid record key");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_did(value: &str) -> bool {
+    let mut parts = value.splitn(3, ':');
+    parts.next() == Some("did")
+        && parts.next().is_some_and(|method| {
+            !method.is_empty() && method.chars().all(|c| c.is_ascii_lowercase())
+        })
+        && parts.next().is_some_and(|identifier| {
+            !identifier.is_empty()
+                && !identifier.ends_with(':')
+                && identifier
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | ':' | '%' | '-'))
+        })
+}
+
+fn is_handle(value: &str) -> bool {
+    let segments: Vec<&str> = value.split('.').collect();
+    segments.len() >= 2
+        && value.len() <= 253
+        && segments.iter().all(|segment| {
+            (1..=63).contains(&segment.len())
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && !segment.starts_with('-')
+                && !segment.ends_with('-')
+        })
+}
+
+fn is_nsid(value: &str) -> bool {
+    let segments: Vec<&str> = value.split('.').collect();
+    let Some((name, authority)) = segments.split_last() else {
+        return false;
+    };
+    segments.len() >= 3
+        && authority.iter().all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+impl FromStr for AtUri {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some(path) = s.strip_prefix("at://") else {
+            return Err(ParseError::new("AT-URI", "Must start with at://"));
+        };
+        let mut parts = path.split('/');
+        let uri = AtUri {
+            authority: parts.next().unwrap_or_default().to_string(),
+            collection: parts.next().map(str::to_string),
+            rkey: parts.next().map(str::to_string),
+        };
+        if parts.next().is_some() {
+            return Err(ParseError::new("AT-URI", "Too many path segments"));
+        }
+        uri.validate()?;
+        Ok(uri)
+    }
+}
+
+impl TryFrom<String> for AtUri {
+    type Error = ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<AtUri> for String {
+    fn from(value: AtUri) -> Self {
+        value.to_string()
+    }
+}
+
+impl Display for AtUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at://{}", self.authority)?;
+        if let Some(collection) = &self.collection {
+            write!(f, "/{collection}")?;
+        }
+        if let Some(rkey) = &self.rkey {
+            write!(f, "/{rkey}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FeedResult {
     pub cursor: Option<String>,
-    pub feed: Vec<Uri>,
+    pub feed: Vec<SkeletonItem>,
+}
+
+/// A post in a feed response.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SkeletonItem {
+    pub post: Uri,
+    /// Context for this item, passed back to the feed generator alongside interactions with it.
+    pub feed_context: Option<String>,
+    /// The repost this post is shown for. Clients show the post as "reposted by" the repost's author.
+    pub repost: Option<Uri>,
+}
+
+impl From<Uri> for SkeletonItem {
+    fn from(post: Uri) -> Self {
+        SkeletonItem {
+            post,
+            feed_context: None,
+            repost: None,
+        }
+    }
+}
+
+/// Feedback from a viewer about an item in a feed
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The post that was interacted with
+    pub item: Option<Uri>,
+    pub event: Option<InteractionEvent>,
+    /// The `feed_context` of the `SkeletonItem` that was interacted with
+    pub feed_context: Option<String>,
+}
+
+impl Interaction {
+    pub(crate) fn from_atrium(value: atrium_api::app::bsky::feed::defs::InteractionData) -> Self {
+        Interaction {
+            item: value.item.map(Uri),
+            event: value.event.map(InteractionEvent::from),
+            feed_context: value.feed_context,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum InteractionEvent {
+    /// The viewer asked for less content like this item
+    RequestLess,
+    /// The viewer asked for more content like this item
+    RequestMore,
+    ClickthroughItem,
+    ClickthroughAuthor,
+    ClickthroughReposter,
+    ClickthroughEmbed,
+    Seen,
+    Like,
+    Repost,
+    Reply,
+    Quote,
+    Share,
+    Other(String),
+}
+
+impl From<String> for InteractionEvent {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "app.bsky.feed.defs#requestLess" => InteractionEvent::RequestLess,
+            "app.bsky.feed.defs#requestMore" => InteractionEvent::RequestMore,
+            "app.bsky.feed.defs#clickthroughItem" => InteractionEvent::ClickthroughItem,
+            "app.bsky.feed.defs#clickthroughAuthor" => InteractionEvent::ClickthroughAuthor,
+            "app.bsky.feed.defs#clickthroughReposter" => InteractionEvent::ClickthroughReposter,
+            "app.bsky.feed.defs#clickthroughEmbed" => InteractionEvent::ClickthroughEmbed,
+            "app.bsky.feed.defs#interactionSeen" => InteractionEvent::Seen,
+            "app.bsky.feed.defs#interactionLike" => InteractionEvent::Like,
+            "app.bsky.feed.defs#interactionRepost" => InteractionEvent::Repost,
+            "app.bsky.feed.defs#interactionReply" => InteractionEvent::Reply,
+            "app.bsky.feed.defs#interactionQuote" => InteractionEvent::Quote,
+            "app.bsky.feed.defs#interactionShare" => InteractionEvent::Share,
+            _ => InteractionEvent::Other(value),
+        }
+    }
+}
+
+impl From<InteractionEvent> for String {
+    fn from(value: InteractionEvent) -> Self {
+        let event = match value {
+            InteractionEvent::RequestLess => "requestLess",
+            InteractionEvent::RequestMore => "requestMore",
+            InteractionEvent::ClickthroughItem => "clickthroughItem",
+            InteractionEvent::ClickthroughAuthor => "clickthroughAuthor",
+            InteractionEvent::ClickthroughReposter => "clickthroughReposter",
+            InteractionEvent::ClickthroughEmbed => "clickthroughEmbed",
+            InteractionEvent::Seen => "interactionSeen",
+            InteractionEvent::Like => "interactionLike",
+            InteractionEvent::Repost => "interactionRepost",
+            InteractionEvent::Reply => "interactionReply",
+            InteractionEvent::Quote => "interactionQuote",
+            InteractionEvent::Share => "interactionShare",
+            InteractionEvent::Other(other) => return other,
+        };
+        format!("app.bsky.feed.defs#{event}")
+    }
+}
+
+/// An error serving a feed request.
+///
+/// Each variant is sent to the requester as an XRPC error response.
+#[derive(Debug, Clone)]
+pub enum FeedError {
+    /// The request's cursor is invalid or has expired. Responds with `400 InvalidCursor`.
+    InvalidCursor,
+    /// The requested feed isn't served by this feed generator. Responds with `400 UnknownFeed`.
+    UnknownFeed,
+    /// Something went wrong while serving the feed. The message is logged, but isn't sent to the requester.
+    /// Responds with `500 InternalServerError`.
+    Internal(String),
+    /// Responds with the given HTTP status code, XRPC error name & message.
+    ///
+    /// `status` must be a 4xx or 5xx status code, any other status responds with `400 Bad Request`.
+    Custom {
+        status: u16,
+        error: String,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for FeedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeedError::InvalidCursor => write!(f, "Invalid cursor"),
+            FeedError::UnknownFeed => write!(f, "Unknown feed"),
+            FeedError::Internal(message) => write!(f, "Internal error: {message}"),
+            FeedError::Custom { error, message, .. } => write!(f, "{error}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for FeedError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn parses_cids() {
+        let text = "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm";
+        let cid: Cid = text.parse().unwrap();
+        assert_eq!(cid.to_string(), text);
+        assert_eq!(serde_json::to_string(&cid).unwrap(), format!("\"{text}\""));
+        assert!("\"bafyrei\"".parse::<Cid>().is_err());
+        // CIDv0
+        assert!("QmdfTbBqBPQ7VNxZEYEj14VmRuZBkqFbiwReogJgS1zR1n"
+            .parse::<Cid>()
+            .is_err());
+    }
+
+    #[test]
+    fn matches_language_tags() {
+        let en: LanguageTag = "en".parse().unwrap();
+        let en_us: LanguageTag = "en-US".parse().unwrap();
+        assert!(en.matches(&en_us));
+        assert!(en.matches(&"EN".parse().unwrap()));
+        assert!(!en_us.matches(&en));
+        assert!(!en.matches(&"eng".parse().unwrap()));
+        assert_eq!(en_us.primary_subtag(), "en");
+        assert!("\"en\"".parse::<LanguageTag>().is_err());
+        assert!("".parse::<LanguageTag>().is_err());
+    }
+
+    #[test]
+    fn parses_at_uris() {
+        let text = "at://did:plc:ewvi7nxzyoun6zhxrhs64oiz/app.bsky.feed.post/3k2a4yq3ujf2e";
+        let uri: AtUri = text.parse().unwrap();
+        assert_eq!(uri.did().unwrap().0, "did:plc:ewvi7nxzyoun6zhxrhs64oiz");
+        assert_eq!(uri.collection(), Some("app.bsky.feed.post"));
+        assert_eq!(uri.rkey(), Some("3k2a4yq3ujf2e"));
+        assert!(uri.is_post());
+        assert_eq!(uri.to_string(), text);
+
+        let profile: AtUri = "at://alice.bsky.social".parse().unwrap();
+        assert!(profile.did().is_none());
+        assert_eq!(profile.collection(), None);
+
+        for invalid in [
+            "https://bsky.app",
+            "at://did:plc:alice/app.bsky.feed.post/3k2a/extra",
+            "at://did:plc:alice/not-an-nsid/3k2a",
+            "at://did:plc:alice/app.bsky.feed.post/..",
+            "at://did:plc:alice/app.bsky.feed.post/",
+            "at://alice/app.bsky.feed.post/3k2a",
+        ] {
+            assert!(invalid.parse::<AtUri>().is_err(), "{invalid}");
+        }
+    }
+
+    fn post() -> Post {
+        let mut post = test_util::post("Cats!");
+        post.labels = vec![Label::Porn, Label::Other("spam".to_string())];
+        post.embed = Some(Embed::External(ExternalEmbed {
+            title: "Cats".to_string(),
+            description: String::new(),
+            uri: "https://cats.example.com".to_string(),
+            thumbnail: None,
+        }));
+        post
+    }
+
+    #[test]
+    fn clamps_future_dated_posts() {
+        let received = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut post = post();
+        assert_eq!(post.effective_time(), post.created_at);
+        assert!(!post.is_backdated(Duration::from_secs(60)));
+
+        post.created_at = received + TimeDelta::days(365);
+        assert_eq!(post.effective_time(), received);
+        assert!(post.is_future_dated(Duration::from_secs(60)));
+        assert!(!post.is_backdated(Duration::from_secs(60)));
+
+        post.created_at = received - TimeDelta::days(365);
+        assert_eq!(post.effective_time(), post.created_at);
+        assert!(post.is_backdated(Duration::from_secs(60)));
+        assert!(!post.is_future_dated(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn keeps_blob_metadata_of_videos() {
+        let embed: Union<RecordEmbedRefs> = serde_json::from_value(serde_json::json!({
+            "$type": "app.bsky.embed.video",
+            "alt": "A cat",
+            "aspectRatio": { "width": 1920, "height": 1080 },
+            "captions": [{
+                "lang": "en",
+                "file": {
+                    "$type": "blob",
+                    "ref": { "$link": "bafyreiabaeaqcaibaeaqcaibaeaqcaibaeaqcaibaeaqcaibaeaqcaibae" },
+                    "mimeType": "text/vtt",
+                    "size": 120
+                }
+            }],
+            "video": {
+                "$type": "blob",
+                "ref": { "$link": "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm" },
+                "mimeType": "video/mp4",
+                "size": 1048576
+            }
+        }))
+        .unwrap();
+        let Some(Embed::Video(video)) = Embed::from_atrium(&embed) else {
+            panic!("Expected a video embed");
+        };
+        assert_eq!(video.mime_type, "video/mp4");
+        assert_eq!(video.size, 1_048_576);
+        assert_eq!(
+            video.aspect_ratio,
+            Some(AspectRatio {
+                width: 1920,
+                height: 1080
+            })
+        );
+        assert_eq!(
+            video
+                .caption_languages()
+                .map(|lang| lang.as_ref())
+                .collect::<Vec<_>>(),
+            vec!["en"]
+        );
+        assert_eq!(
+            video.playlist_url(&Did("did:plc:alice".to_string())),
+            "https://video.bsky.app/watch/did%3Aplc%3Aalice/bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm/playlist.m3u8"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_embeds_and_legacy_blobs() {
+        let embed: Union<RecordEmbedRefs> = serde_json::from_value(serde_json::json!({
+            "$type": "app.bsky.embed.poll",
+            "options": ["Cats", "Dogs"]
+        }))
+        .unwrap();
+        let Some(Embed::Unknown { r#type, raw_json }) = Embed::from_atrium(&embed) else {
+            panic!("Expected an unknown embed");
+        };
+        assert_eq!(r#type, "app.bsky.embed.poll");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&raw_json).unwrap()["options"],
+            serde_json::json!(["Cats", "Dogs"])
+        );
+
+        let embed: Union<RecordEmbedRefs> = serde_json::from_value(serde_json::json!({
+            "$type": "app.bsky.embed.images",
+            "images": [{
+                "alt": "A cat",
+                "image": {
+                    "cid": "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm",
+                    "mimeType": "image/jpeg"
+                }
+            }]
+        }))
+        .unwrap();
+        let images = Embed::from_atrium(&embed).unwrap().images().to_vec();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].mime_type, "image/jpeg");
+        assert_eq!(images[0].size, 0);
+
+        let labels: Union<RecordLabelsRefs> = serde_json::from_value(serde_json::json!({
+            "$type": "com.example.labels",
+            "values": [{ "val": "porn" }, { "val": "spam" }]
+        }))
+        .unwrap();
+        assert_eq!(
+            Label::from_atrium(&labels),
+            Some(vec![Label::Porn, Label::Other("spam".to_string())])
+        );
+    }
+
+    #[test]
+    fn serializes_posts_as_json() {
+        let post = post();
+        let json = serde_json::to_value(&post).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "author_did": "did:plc:alice",
+                "cid": "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm",
+                "uri": "at://did:plc:alice/app.bsky.feed.post/3k2a",
+                "text": "Cats!",
+                "labels": ["porn", "spam"],
+                "langs": ["en-US"],
+                "timestamp": "2023-11-14T22:13:20Z",
+                "created_at": "2023-11-14T22:13:19Z",
+                "embed": {
+                    "external": {
+                        "title": "Cats",
+                        "description": "",
+                        "uri": "https://cats.example.com",
+                        "thumbnail": null
+                    }
+                },
+                "reply": null,
+                "facets": []
+            })
+        );
+        assert_eq!(serde_json::from_value::<Post>(json).unwrap(), post);
+    }
 }
@@ -0,0 +1,535 @@
+use std::collections::HashSet;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::feed_handler::FeedHandler;
+use crate::models::{
+    Did, Embed, FeedError, FeedResult, Interaction, Label, LanguageTag, MediaEmbed, Post, Request,
+    Uri,
+};
+
+/// Decides which posts a feed is interested in.
+///
+/// Combine filters with `and`, `or` & `not`, then wrap your handler in a `FilteredHandler`.
+/// Closures taking a `&Post` & returning `bool` are filters too.
+///
+/// ```
+/// use skyfeed::{IsReply, Keywords, Language, PostFilter, Unlabeled};
+///
+/// let filter = Keywords::new(["cat", "cats", "kitten"])
+///     .and(Language::new(["en"]))
+///     .and(IsReply.not())
+///     .and(Unlabeled);
+/// ```
+pub trait PostFilter: Send + Sync {
+    /// Returns true if the post passes this filter.
+    fn matches(&self, post: &Post) -> bool;
+
+    /// Passes posts that pass both filters.
+    fn and<Other: PostFilter>(self, other: Other) -> And<Self, Other>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    /// Passes posts that pass either filter.
+    fn or<Other: PostFilter>(self, other: Other) -> Or<Self, Other>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    /// Passes posts that don't pass this filter.
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+impl<F: Fn(&Post) -> bool + Send + Sync> PostFilter for F {
+    fn matches(&self, post: &Post) -> bool {
+        self(post)
+    }
+}
+
+/// Passes posts that pass both filters, see `PostFilter::and`
+#[derive(Debug, Clone)]
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: PostFilter, B: PostFilter> PostFilter for And<A, B> {
+    fn matches(&self, post: &Post) -> bool {
+        self.0.matches(post) && self.1.matches(post)
+    }
+}
+
+/// Passes posts that pass either filter, see `PostFilter::or`
+#[derive(Debug, Clone)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: PostFilter, B: PostFilter> PostFilter for Or<A, B> {
+    fn matches(&self, post: &Post) -> bool {
+        self.0.matches(post) || self.1.matches(post)
+    }
+}
+
+/// Passes posts that don't pass the filter, see `PostFilter::not`
+#[derive(Debug, Clone)]
+pub struct Not<F>(pub F);
+
+impl<F: PostFilter> PostFilter for Not<F> {
+    fn matches(&self, post: &Post) -> bool {
+        !self.0.matches(post)
+    }
+}
+
+/// Passes posts whose text matches a regex.
+#[derive(Debug, Clone)]
+pub struct TextMatches(Regex);
+
+impl TextMatches {
+    pub fn new(regex: Regex) -> Self {
+        TextMatches(regex)
+    }
+}
+
+impl PostFilter for TextMatches {
+    fn matches(&self, post: &Post) -> bool {
+        self.0.is_match(&post.text)
+    }
+}
+
+/// Passes posts whose text contains any of the keywords as a whole word, ignoring case.
+#[derive(Debug, Clone)]
+pub struct Keywords(Regex);
+
+impl Keywords {
+    pub fn new(keywords: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        let keywords: Vec<String> = keywords
+            .into_iter()
+            .map(|keyword| word_pattern(keyword.as_ref()))
+            .collect();
+        // An empty alternation would match every post
+        let pattern = if keywords.is_empty() {
+            "[^\\s\\S]".to_string()
+        } else {
+            format!("(?:{})", keywords.join("|"))
+        };
+        Keywords(
+            RegexBuilder::new(&pattern)
+                .case_insensitive(true)
+                .build()
+                .expect("Escaped keywords are a valid regex"),
+        )
+    }
+}
+
+impl PostFilter for Keywords {
+    fn matches(&self, post: &Post) -> bool {
+        self.0.is_match(&post.text)
+    }
+}
+
+/// Matches `keyword` as a whole word. Word boundaries only apply to edges that are word characters, so `#cats` & `c++` match too.
+fn word_pattern(keyword: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let start = if keyword.starts_with(is_word) {
+        "\\b"
+    } else {
+        ""
+    };
+    let end = if keyword.ends_with(is_word) {
+        "\\b"
+    } else {
+        ""
+    };
+    format!("{start}{}{end}", regex::escape(keyword))
+}
+
+/// Passes posts in any of the languages, given as BCP-47 tags like `en` or `pt-BR`.
+///
+/// A tag also matches more specific variants, `en` matches `en-US`. See `LanguageTag::matches`.
+#[derive(Debug, Clone)]
+pub struct Language(Vec<LanguageTag>);
+
+impl Language {
+    /// # Panics
+    ///
+    /// Panics if any of the languages isn't a valid language tag.
+    pub fn new(languages: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        Language(
+            languages
+                .into_iter()
+                .map(|language| language.as_ref().parse().unwrap_or_else(|e| panic!("{e}")))
+                .collect(),
+        )
+    }
+}
+
+impl PostFilter for Language {
+    fn matches(&self, post: &Post) -> bool {
+        post.langs
+            .iter()
+            .any(|lang| self.0.iter().any(|wanted| wanted.matches(lang)))
+    }
+}
+
+/// Passes posts with none of the labels.
+#[derive(Debug, Clone)]
+pub struct ExcludeLabels(Vec<Label>);
+
+impl ExcludeLabels {
+    pub fn new(labels: impl IntoIterator<Item = Label>) -> Self {
+        ExcludeLabels(labels.into_iter().collect())
+    }
+}
+
+impl PostFilter for ExcludeLabels {
+    fn matches(&self, post: &Post) -> bool {
+        !post.labels.iter().any(|label| self.0.contains(label))
+    }
+}
+
+/// Passes posts without any labels.
+#[derive(Debug, Clone, Copy)]
+pub struct Unlabeled;
+
+impl PostFilter for Unlabeled {
+    fn matches(&self, post: &Post) -> bool {
+        post.labels.is_empty()
+    }
+}
+
+/// A kind of embedded content, see `HasEmbed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbedKind {
+    Images,
+    Video,
+    External,
+    Quote,
+}
+
+/// Passes posts that embed the kind of content. A quote with media has both the quote & media kinds.
+/// Unknown embed types don't match any kind.
+#[derive(Debug, Clone, Copy)]
+pub struct HasEmbed(pub EmbedKind);
+
+impl PostFilter for HasEmbed {
+    fn matches(&self, post: &Post) -> bool {
+        let media_kind = |media: &MediaEmbed| match media {
+            MediaEmbed::Images(_) => Some(EmbedKind::Images),
+            MediaEmbed::Video(_) => Some(EmbedKind::Video),
+            MediaEmbed::External(_) => Some(EmbedKind::External),
+            MediaEmbed::Unknown { .. } => None,
+        };
+        match &post.embed {
+            None => false,
+            Some(Embed::Images(_)) => self.0 == EmbedKind::Images,
+            Some(Embed::Video(_)) => self.0 == EmbedKind::Video,
+            Some(Embed::External(_)) => self.0 == EmbedKind::External,
+            Some(Embed::Quote(_)) => self.0 == EmbedKind::Quote,
+            Some(Embed::QuoteWithMedia(_, media)) => {
+                self.0 == EmbedKind::Quote || Some(self.0) == media_kind(media)
+            }
+            Some(Embed::Unknown { .. }) => false,
+        }
+    }
+}
+
+/// Passes or blocks posts by their author's DID.
+#[derive(Debug, Clone)]
+pub struct Authors {
+    dids: HashSet<String>,
+    allow: bool,
+}
+
+impl Authors {
+    /// Only passes posts by these authors
+    pub fn allow(dids: impl IntoIterator<Item = Did>) -> Self {
+        Authors {
+            dids: dids.into_iter().map(|did| did.0).collect(),
+            allow: true,
+        }
+    }
+
+    /// Passes posts by anyone except these authors
+    pub fn deny(dids: impl IntoIterator<Item = Did>) -> Self {
+        Authors {
+            dids: dids.into_iter().map(|did| did.0).collect(),
+            allow: false,
+        }
+    }
+}
+
+impl PostFilter for Authors {
+    fn matches(&self, post: &Post) -> bool {
+        self.dids.contains(&post.author_did.0) == self.allow
+    }
+}
+
+/// Passes replies. Use `IsReply.not()` for top-level posts.
+#[derive(Debug, Clone, Copy)]
+pub struct IsReply;
+
+impl PostFilter for IsReply {
+    fn matches(&self, post: &Post) -> bool {
+        post.is_reply()
+    }
+}
+
+/// Only passes posts that match `filter` on to `handler`. All other firehose input & feed requests are passed on unchanged.
+#[derive(Debug, Clone)]
+pub struct FilteredHandler<Filter, Handler> {
+    filter: Filter,
+    handler: Handler,
+}
+
+impl<Filter: PostFilter, Handler: FeedHandler> FilteredHandler<Filter, Handler> {
+    pub fn new(filter: Filter, handler: Handler) -> Self {
+        FilteredHandler { filter, handler }
+    }
+}
+
+impl<Filter: PostFilter, Handler: FeedHandler + Send + Sync> FeedHandler
+    for FilteredHandler<Filter, Handler>
+{
+    async fn insert_post(&mut self, post: Post) {
+        if self.filter.matches(&post) {
+            self.handler.insert_post(post).await;
+        }
+    }
+
+    async fn delete_post(&mut self, uri: Uri) {
+        self.handler.delete_post(uri).await;
+    }
+
+    async fn like_post(&mut self, like_uri: Uri, liked_post_uri: Uri) {
+        self.handler.like_post(like_uri, liked_post_uri).await;
+    }
+
+    async fn delete_like(&mut self, like_uri: Uri) {
+        self.handler.delete_like(like_uri).await;
+    }
+
+    async fn repost_post(&mut self, repost_uri: Uri, reposted_post_uri: Uri) {
+        self.handler
+            .repost_post(repost_uri, reposted_post_uri)
+            .await;
+    }
+
+    async fn delete_repost(&mut self, repost_uri: Uri) {
+        self.handler.delete_repost(repost_uri).await;
+    }
+
+    async fn follow(&mut self, follow_uri: Uri, follower: Did, followed: Did) {
+        self.handler.follow(follow_uri, follower, followed).await;
+    }
+
+    async fn unfollow(&mut self, follow_uri: Uri) {
+        self.handler.unfollow(follow_uri).await;
+    }
+
+    async fn block(&mut self, block_uri: Uri, blocker: Did, blocked: Did) {
+        self.handler.block(block_uri, blocker, blocked).await;
+    }
+
+    async fn unblock(&mut self, block_uri: Uri) {
+        self.handler.unblock(block_uri).await;
+    }
+
+    async fn shutdown(&mut self) {
+        self.handler.shutdown().await;
+    }
+
+    async fn ready(&self) -> bool {
+        self.handler.ready().await
+    }
+
+    async fn on_interactions(&self, viewer: Did, interactions: Vec<Interaction>) {
+        self.handler.on_interactions(viewer, interactions).await;
+    }
+
+    async fn serve_feed(&self, request: Request) -> Result<FeedResult, FeedError> {
+        self.handler.serve_feed(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Cid, PostRef, QuoteEmbed, Reply, VideoEmbed};
+    use crate::test_util::{post, RecordingHandler, CID};
+
+    fn cid() -> Cid {
+        CID.parse().unwrap()
+    }
+
+    fn quote() -> QuoteEmbed {
+        QuoteEmbed {
+            cid: cid(),
+            uri: "at://did:plc:bob/app.bsky.feed.post/quoted".to_string(),
+        }
+    }
+
+    fn video() -> VideoEmbed {
+        VideoEmbed {
+            cid: cid(),
+            alt_text: String::new(),
+            mime_type: "video/mp4".to_string(),
+            size: 1024,
+            aspect_ratio: None,
+            captions: vec![],
+        }
+    }
+
+    #[test]
+    fn matches_text() {
+        let filter = TextMatches::new(Regex::new("^[Cc]ats?!").unwrap());
+        assert!(filter.matches(&post("Cats! Everywhere")));
+        assert!(!filter.matches(&post("I like cats!")));
+    }
+
+    #[test]
+    fn matches_keywords_as_whole_words() {
+        let filter = Keywords::new(["cat", "#kitten", "c++"]);
+        for text in [
+            "A CAT on the mat",
+            "cat",
+            "I love #kitten pics",
+            "#kitten",
+            "learning c++ today",
+            "c++, again",
+        ] {
+            assert!(filter.matches(&post(text)), "{text}");
+        }
+        for text in [
+            "concatenate",
+            "cats",
+            "#kittens",
+            "kitten",
+            "learning c+ today",
+        ] {
+            assert!(!filter.matches(&post(text)), "{text}");
+        }
+        assert!(!Keywords::new(Vec::<String>::new()).matches(&post("cat")));
+    }
+
+    #[test]
+    fn matches_languages() {
+        assert!(Language::new(["en"]).matches(&post("Cats")));
+        assert!(Language::new(["pt", "EN-us"]).matches(&post("Cats")));
+        assert!(!Language::new(["en-GB"]).matches(&post("Cats")));
+
+        let mut unknown_language = post("Cats");
+        unknown_language.langs.clear();
+        assert!(!Language::new(["en"]).matches(&unknown_language));
+    }
+
+    #[test]
+    fn matches_labels() {
+        let mut labeled = post("Cats");
+        labeled.labels = vec![Label::Porn];
+        assert!(!ExcludeLabels::new([Label::Porn, Label::GraphicMedia]).matches(&labeled));
+        assert!(ExcludeLabels::new([Label::GraphicMedia]).matches(&labeled));
+        assert!(!Unlabeled.matches(&labeled));
+        assert!(Unlabeled.matches(&post("Cats")));
+    }
+
+    #[test]
+    fn matches_embed_kinds() {
+        let kinds = [
+            EmbedKind::Images,
+            EmbedKind::Video,
+            EmbedKind::External,
+            EmbedKind::Quote,
+        ];
+        let matching = |embed: Option<Embed>| {
+            let mut post = post("Cats");
+            post.embed = embed;
+            kinds
+                .into_iter()
+                .filter(|kind| HasEmbed(*kind).matches(&post))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matching(None), vec![]);
+        assert_eq!(
+            matching(Some(Embed::Video(video()))),
+            vec![EmbedKind::Video]
+        );
+        assert_eq!(
+            matching(Some(Embed::QuoteWithMedia(
+                quote(),
+                MediaEmbed::Video(video())
+            ))),
+            vec![EmbedKind::Video, EmbedKind::Quote]
+        );
+        assert_eq!(
+            matching(Some(Embed::Unknown {
+                r#type: "app.bsky.embed.poll".to_string(),
+                raw_json: "{}".to_string(),
+            })),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn allows_and_denies_authors() {
+        let alice = || Did("did:plc:alice".to_string());
+        let bob = || Did("did:plc:bob".to_string());
+        assert!(Authors::allow([alice()]).matches(&post("Cats")));
+        assert!(!Authors::allow([bob()]).matches(&post("Cats")));
+        assert!(!Authors::deny([alice(), bob()]).matches(&post("Cats")));
+        assert!(Authors::deny([bob()]).matches(&post("Cats")));
+    }
+
+    #[test]
+    fn matches_replies() {
+        let mut reply = post("Cats");
+        let parent = PostRef {
+            uri: Uri("at://did:plc:bob/app.bsky.feed.post/parent".to_string()),
+            cid: cid(),
+        };
+        reply.reply = Some(Reply {
+            parent: parent.clone(),
+            root: parent,
+        });
+        assert!(IsReply.matches(&reply));
+        assert!(!IsReply.matches(&post("Cats")));
+        assert!(IsReply.not().matches(&post("Cats")));
+    }
+
+    #[test]
+    fn combines_filters() {
+        let cats = || Keywords::new(["cats"]);
+        let dogs = || Keywords::new(["dogs"]);
+        assert!(cats().and(Unlabeled).matches(&post("cats")));
+        assert!(!cats().and(dogs()).matches(&post("cats")));
+        assert!(cats().or(dogs()).matches(&post("dogs")));
+        assert!(!cats().or(dogs()).matches(&post("birds")));
+        assert!(cats().not().matches(&post("dogs")));
+        assert!(cats()
+            .and(|post: &Post| post.text.len() < 10)
+            .matches(&post("cats")));
+    }
+
+    #[tokio::test]
+    async fn only_passes_matching_posts_to_handler() {
+        let handler = RecordingHandler::default();
+        let mut filtered = FilteredHandler::new(Keywords::new(["cats"]), handler.clone());
+        filtered.insert_post(post("I love cats")).await;
+        filtered.insert_post(post("I love dogs")).await;
+        let uri = Uri("at://did:plc:alice/app.bsky.feed.post/dogs".to_string());
+        filtered.delete_post(uri.clone()).await;
+
+        let inserted = handler.inserted.lock().unwrap();
+        assert_eq!(
+            inserted.iter().map(|post| &post.text).collect::<Vec<_>>(),
+            vec!["I love cats"]
+        );
+        // Deletes are passed on even for posts the filter would've dropped
+        assert_eq!(*handler.deleted.lock().unwrap(), vec![uri]);
+    }
+}
@@ -0,0 +1,452 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Row};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::Mutex;
+
+use crate::models::{Did, FeedError, ParseError, Post, Uri};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS posts (
+        uri TEXT PRIMARY KEY,
+        cid TEXT NOT NULL,
+        author_did TEXT NOT NULL,
+        text TEXT NOT NULL,
+        labels TEXT NOT NULL,
+        langs TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        embed TEXT NOT NULL,
+        reply TEXT NOT NULL,
+        facets TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS posts_timestamp ON posts (timestamp);
+    CREATE INDEX IF NOT EXISTS posts_author ON posts (author_did, timestamp);
+    CREATE TABLE IF NOT EXISTS likes (
+        like_uri TEXT PRIMARY KEY,
+        post_uri TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS likes_post ON likes (post_uri);
+    CREATE TABLE IF NOT EXISTS reposts (
+        repost_uri TEXT PRIMARY KEY,
+        post_uri TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS reposts_post ON reposts (post_uri);
+";
+
+const POST_COLUMNS: &str =
+    "posts.uri, posts.cid, posts.author_did, posts.text, posts.labels, posts.langs, posts.timestamp, posts.embed, posts.reply, posts.facets, posts.created_at";
+
+/// Stores posts, likes & reposts in a SQLite database.
+///
+/// Your `FeedHandler` decides which posts to store & forwards likes & reposts, then serves feeds from the query helpers.
+/// Likes & reposts are only stored for posts in the store.
+///
+/// Clones share the same database connection.
+///
+/// Queries run on the calling task while it holds the connection, so each call blocks its runtime thread until SQLite returns.
+/// Run heavy custom queries from `tokio::task::spawn_blocking` or `tokio::task::block_in_place` to keep other tasks responsive.
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    db: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// Opens or creates the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Opens a database that only lives in memory, useful for tests
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// Uses an existing connection, creating any missing tables
+    pub fn from_connection(db: Connection) -> rusqlite::Result<Self> {
+        db.execute_batch(SCHEMA)?;
+        Ok(SqliteStore {
+            db: Arc::new(Mutex::new(db)),
+        })
+    }
+
+    /// Runs custom queries against the database
+    pub async fn with_connection<T>(&self, f: impl FnOnce(&mut Connection) -> T) -> T {
+        f(&mut *self.db.lock().await)
+    }
+
+    /// Stores a post, replacing any stored post with the same URI
+    pub async fn insert_post(&self, post: &Post) -> rusqlite::Result<()> {
+        let db = self.db.lock().await;
+        db.execute(
+            "INSERT OR REPLACE INTO posts (uri, cid, author_did, text, labels, langs, timestamp, embed, reply, facets, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                post.uri.0,
+                post.cid.to_string(),
+                post.author_did.0,
+                post.text,
+                to_json(&post.labels)?,
+                to_json(&post.langs)?,
+                post.timestamp.timestamp_micros(),
+                to_json(&post.embed)?,
+                to_json(&post.reply)?,
+                to_json(&post.facets)?,
+                post.created_at.timestamp_micros(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Deletes a post along with its likes & reposts
+    pub async fn delete_post(&self, uri: &Uri) -> rusqlite::Result<()> {
+        let mut db = self.db.lock().await;
+        let transaction = db.transaction()?;
+        transaction.execute("DELETE FROM posts WHERE uri = ?1", params![uri.0])?;
+        transaction.execute("DELETE FROM likes WHERE post_uri = ?1", params![uri.0])?;
+        transaction.execute("DELETE FROM reposts WHERE post_uri = ?1", params![uri.0])?;
+        transaction.commit()
+    }
+
+    /// Stores a like if the liked post is in the store
+    pub async fn like_post(&self, like_uri: &Uri, liked_post_uri: &Uri) -> rusqlite::Result<()> {
+        let db = self.db.lock().await;
+        db.execute(
+            "INSERT OR IGNORE INTO likes (like_uri, post_uri)
+             SELECT ?1, ?2
+             WHERE EXISTS (SELECT 1 FROM posts WHERE uri = ?2)",
+            params![like_uri.0, liked_post_uri.0],
+        )?;
+        Ok(())
+    }
+
+    pub async fn delete_like(&self, like_uri: &Uri) -> rusqlite::Result<()> {
+        let db = self.db.lock().await;
+        db.execute("DELETE FROM likes WHERE like_uri = ?1", params![like_uri.0])?;
+        Ok(())
+    }
+
+    /// Stores a repost if the reposted post is in the store
+    pub async fn repost_post(
+        &self,
+        repost_uri: &Uri,
+        reposted_post_uri: &Uri,
+    ) -> rusqlite::Result<()> {
+        let db = self.db.lock().await;
+        db.execute(
+            "INSERT OR IGNORE INTO reposts (repost_uri, post_uri)
+             SELECT ?1, ?2
+             WHERE EXISTS (SELECT 1 FROM posts WHERE uri = ?2)",
+            params![repost_uri.0, reposted_post_uri.0],
+        )?;
+        Ok(())
+    }
+
+    pub async fn delete_repost(&self, repost_uri: &Uri) -> rusqlite::Result<()> {
+        let db = self.db.lock().await;
+        db.execute(
+            "DELETE FROM reposts WHERE repost_uri = ?1",
+            params![repost_uri.0],
+        )?;
+        Ok(())
+    }
+
+    /// Deletes posts older than `max_age` along with their likes & reposts, returning the number of deleted posts
+    pub async fn prune_older_than(&self, max_age: Duration) -> rusqlite::Result<usize> {
+        let cutoff = Utc::now().timestamp_micros() - max_age.as_micros() as i64;
+        self.prune("DELETE FROM posts WHERE timestamp < ?1", cutoff)
+            .await
+    }
+
+    /// Deletes all but the `max_posts` newest posts along with their likes & reposts, returning the number of deleted posts
+    pub async fn prune_to_count(&self, max_posts: usize) -> rusqlite::Result<usize> {
+        self.prune(
+            "DELETE FROM posts WHERE uri NOT IN (SELECT uri FROM posts ORDER BY timestamp DESC LIMIT ?1)",
+            max_posts as i64,
+        )
+        .await
+    }
+
+    async fn prune(&self, delete_posts: &str, parameter: i64) -> rusqlite::Result<usize> {
+        let mut db = self.db.lock().await;
+        let transaction = db.transaction()?;
+        let deleted = transaction.execute(delete_posts, params![parameter])?;
+        transaction.execute(
+            "DELETE FROM likes WHERE post_uri NOT IN (SELECT uri FROM posts)",
+            [],
+        )?;
+        transaction.execute(
+            "DELETE FROM reposts WHERE post_uri NOT IN (SELECT uri FROM posts)",
+            [],
+        )?;
+        transaction.commit()?;
+        Ok(deleted)
+    }
+
+    /// The newest posts, optionally only those posted before `before` to page through older posts
+    pub async fn recent_posts(
+        &self,
+        limit: usize,
+        before: Option<DateTime<Utc>>,
+    ) -> rusqlite::Result<Vec<Post>> {
+        let db = self.db.lock().await;
+        let mut statement = db.prepare_cached(&format!(
+            "SELECT {POST_COLUMNS} FROM posts
+             WHERE timestamp < ?1
+             ORDER BY timestamp DESC
+             LIMIT ?2"
+        ))?;
+        let before = before.map_or(i64::MAX, |before| before.timestamp_micros());
+        let posts = statement
+            .query_map(params![before, limit as i64], post_from_row)?
+            .collect();
+        posts
+    }
+
+    /// The most liked posts from the last `window` with their like counts, most liked first
+    pub async fn top_by_likes(
+        &self,
+        window: Duration,
+        limit: usize,
+    ) -> rusqlite::Result<Vec<(Post, u64)>> {
+        let db = self.db.lock().await;
+        let mut statement = db.prepare_cached(&format!(
+            "SELECT {POST_COLUMNS}, COUNT(likes.like_uri) AS like_count
+             FROM posts
+             JOIN likes ON likes.post_uri = posts.uri
+             WHERE posts.timestamp >= ?1
+             GROUP BY posts.uri
+             ORDER BY like_count DESC, posts.timestamp DESC
+             LIMIT ?2"
+        ))?;
+        let since = Utc::now().timestamp_micros() - window.as_micros() as i64;
+        let posts = statement
+            .query_map(params![since, limit as i64], |row| {
+                Ok((post_from_row(row)?, row.get(11)?))
+            })?
+            .collect();
+        posts
+    }
+
+    /// The newest posts by `author`
+    pub async fn posts_by_author(&self, author: &Did, limit: usize) -> rusqlite::Result<Vec<Post>> {
+        let db = self.db.lock().await;
+        let mut statement = db.prepare_cached(&format!(
+            "SELECT {POST_COLUMNS} FROM posts
+             WHERE author_did = ?1
+             ORDER BY timestamp DESC
+             LIMIT ?2"
+        ))?;
+        let posts = statement
+            .query_map(params![author.0, limit as i64], post_from_row)?
+            .collect();
+        posts
+    }
+
+    pub async fn like_count(&self, uri: &Uri) -> rusqlite::Result<u64> {
+        let db = self.db.lock().await;
+        db.query_row(
+            "SELECT COUNT(*) FROM likes WHERE post_uri = ?1",
+            params![uri.0],
+            |row| row.get(0),
+        )
+    }
+
+    pub async fn repost_count(&self, uri: &Uri) -> rusqlite::Result<u64> {
+        let db = self.db.lock().await;
+        db.query_row(
+            "SELECT COUNT(*) FROM reposts WHERE post_uri = ?1",
+            params![uri.0],
+            |row| row.get(0),
+        )
+    }
+}
+
+impl From<rusqlite::Error> for FeedError {
+    fn from(value: rusqlite::Error) -> Self {
+        FeedError::Internal(value.to_string())
+    }
+}
+
+fn post_from_row(row: &Row) -> rusqlite::Result<Post> {
+    Ok(Post {
+        uri: Uri(row.get(0)?),
+        cid: row.get::<_, String>(1)?.parse().map_err(|e: ParseError| {
+            rusqlite::Error::FromSqlConversionFailure(1, Type::Text, Box::new(e))
+        })?,
+        author_did: Did(row.get(2)?),
+        text: row.get(3)?,
+        labels: from_json(row, 4)?,
+        langs: from_json(row, 5)?,
+        timestamp: datetime_from_micros(row, 6)?,
+        created_at: datetime_from_micros(row, 10)?,
+        embed: from_json(row, 7)?,
+        reply: from_json(row, 8)?,
+        facets: from_json(row, 9)?,
+    })
+}
+
+fn datetime_from_micros(row: &Row, index: usize) -> rusqlite::Result<DateTime<Utc>> {
+    let micros = row.get(index)?;
+    DateTime::from_timestamp_micros(micros).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            index,
+            Type::Integer,
+            format!("Invalid timestamp: {micros}").into(),
+        )
+    })
+}
+
+fn to_json(value: &impl Serialize) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn from_json<T: DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let json: String = row.get(index)?;
+    serde_json::from_str(&json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        AspectRatio, Caption, Embed, Facet, FacetFeature, Label, MediaEmbed, PostRef, QuoteEmbed,
+        Reply, VideoEmbed,
+    };
+    use crate::test_util;
+
+    const QUOTED_CID: &str = "bafyreiacaibaeaqcaibaeaqcaibaeaqcaibaeaqcaibaeaqcaibaeaqcai";
+
+    fn post(author: &str, rkey: &str, age: Duration) -> Post {
+        let mut post = test_util::post("Cats!");
+        post.author_did = Did(author.to_string());
+        post.uri = Uri(format!("at://{author}/app.bsky.feed.post/{rkey}"));
+        post.labels = vec![Label::Porn, Label::Other("spam".to_string())];
+        post.timestamp = Utc::now() - age;
+        post.created_at = post.timestamp;
+        post.embed = Some(Embed::Quote(QuoteEmbed {
+            cid: QUOTED_CID.parse().unwrap(),
+            uri: "at://did:plc:other/app.bsky.feed.post/quoted".to_string(),
+        }));
+        post
+    }
+
+    fn like(rkey: &str) -> Uri {
+        Uri(format!("at://did:plc:liker/app.bsky.feed.like/{rkey}"))
+    }
+
+    #[tokio::test]
+    async fn round_trips_posts() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let mut stored = post("did:plc:alice", "1", Duration::from_secs(10));
+        // Timestamps are stored with microsecond precision
+        stored.timestamp = DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap();
+        stored.created_at = DateTime::from_timestamp_micros(1_699_999_999_000_000).unwrap();
+        let post_ref = PostRef {
+            uri: Uri("at://did:plc:other/app.bsky.feed.post/parent".to_string()),
+            cid: QUOTED_CID.parse().unwrap(),
+        };
+        stored.reply = Some(Reply {
+            parent: post_ref.clone(),
+            root: post_ref,
+        });
+        stored.facets = vec![Facet {
+            byte_start: 0,
+            byte_end: 4,
+            features: vec![
+                FacetFeature::Tag("cats".to_string()),
+                FacetFeature::Mention(Did("did:plc:bob".to_string())),
+            ],
+        }];
+        let Some(Embed::Quote(quote)) = stored.embed.clone() else {
+            unreachable!()
+        };
+        stored.embed = Some(Embed::QuoteWithMedia(
+            quote,
+            MediaEmbed::Video(VideoEmbed {
+                cid: QUOTED_CID.parse().unwrap(),
+                alt_text: "A cat".to_string(),
+                mime_type: "video/mp4".to_string(),
+                size: 1024,
+                aspect_ratio: Some(AspectRatio {
+                    width: 16,
+                    height: 9,
+                }),
+                captions: vec![Caption {
+                    cid: QUOTED_CID.parse().unwrap(),
+                    lang: "pt-BR".parse().unwrap(),
+                    mime_type: "text/vtt".to_string(),
+                    size: 64,
+                }],
+            }),
+        ));
+        store.insert_post(&stored).await.unwrap();
+
+        let posts = store.recent_posts(10, None).await.unwrap();
+        assert_eq!(posts, vec![stored]);
+    }
+
+    #[tokio::test]
+    async fn ranks_posts_by_likes_in_window() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let old = post("did:plc:alice", "old", Duration::from_secs(7200));
+        let liked = post("did:plc:alice", "liked", Duration::from_secs(60));
+        let most_liked = post("did:plc:bob", "most_liked", Duration::from_secs(120));
+        for post in [&old, &liked, &most_liked] {
+            store.insert_post(post).await.unwrap();
+        }
+        for (rkey, post) in [("1", &old), ("2", &old), ("3", &old), ("4", &liked)] {
+            store.like_post(&like(rkey), &post.uri).await.unwrap();
+        }
+        for rkey in ["5", "6"] {
+            store.like_post(&like(rkey), &most_liked.uri).await.unwrap();
+        }
+        // Likes of posts that aren't stored are ignored
+        store
+            .like_post(&like("7"), &Uri("at://unknown".to_string()))
+            .await
+            .unwrap();
+
+        let top = store
+            .top_by_likes(Duration::from_secs(3600), 10)
+            .await
+            .unwrap();
+        let top: Vec<_> = top
+            .iter()
+            .map(|(post, likes)| (&post.uri, *likes))
+            .collect();
+        assert_eq!(top, vec![(&most_liked.uri, 2), (&liked.uri, 1)]);
+
+        let by_bob = store
+            .posts_by_author(&Did("did:plc:bob".to_string()), 10)
+            .await
+            .unwrap();
+        assert_eq!(by_bob.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn prunes_posts_with_their_likes() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let old = post("did:plc:alice", "old", Duration::from_secs(7200));
+        let new = post("did:plc:alice", "new", Duration::from_secs(60));
+        store.insert_post(&old).await.unwrap();
+        store.insert_post(&new).await.unwrap();
+        store.like_post(&like("1"), &old.uri).await.unwrap();
+
+        assert_eq!(
+            store
+                .prune_older_than(Duration::from_secs(3600))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(store.like_count(&old.uri).await.unwrap(), 0);
+        assert_eq!(store.prune_to_count(0).await.unwrap(), 1);
+        assert!(store.recent_posts(10, None).await.unwrap().is_empty());
+    }
+}
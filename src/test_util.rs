@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::DateTime;
+
+use crate::feed_handler::FeedHandler;
+use crate::models::{Did, FeedError, FeedResult, Interaction, Post, Request, Uri};
+
+pub(crate) const CID: &str = "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm";
+
+/// An unlabeled post by `did:plc:alice` without embed, reply or facets, received at a fixed time
+pub(crate) fn post(text: &str) -> Post {
+    Post {
+        author_did: Did("did:plc:alice".to_string()),
+        cid: CID.parse().unwrap(),
+        uri: Uri("at://did:plc:alice/app.bsky.feed.post/3k2a".to_string()),
+        text: text.to_string(),
+        labels: vec![],
+        langs: vec!["en-US".parse().unwrap()],
+        timestamp: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        created_at: DateTime::from_timestamp(1_699_999_999, 0).unwrap(),
+        embed: None,
+        reply: None,
+        facets: vec![],
+    }
+}
+
+pub(crate) type ReceivedInteractions = Vec<(Did, Vec<Interaction>)>;
+
+/// Records the posts & interactions it receives & the viewer of each feed request, serving empty feeds
+#[derive(Clone, Default)]
+pub(crate) struct RecordingHandler {
+    pub(crate) inserted: Arc<Mutex<Vec<Post>>>,
+    pub(crate) deleted: Arc<Mutex<Vec<Uri>>>,
+    pub(crate) viewers: Arc<Mutex<Vec<Option<Did>>>>,
+    pub(crate) interactions: Arc<Mutex<ReceivedInteractions>>,
+    pub(crate) not_ready: bool,
+}
+
+impl FeedHandler for RecordingHandler {
+    async fn insert_post(&mut self, post: Post) {
+        self.inserted.lock().unwrap().push(post);
+    }
+
+    async fn delete_post(&mut self, uri: Uri) {
+        self.deleted.lock().unwrap().push(uri);
+    }
+
+    async fn like_post(&mut self, _like_uri: Uri, _liked_post_uri: Uri) {}
+
+    async fn delete_like(&mut self, _like_uri: Uri) {}
+
+    async fn on_interactions(&self, viewer: Did, interactions: Vec<Interaction>) {
+        self.interactions
+            .lock()
+            .unwrap()
+            .push((viewer, interactions));
+    }
+
+    async fn ready(&self) -> bool {
+        !self.not_ready
+    }
+
+    async fn serve_feed(&self, request: Request) -> Result<FeedResult, FeedError> {
+        self.viewers.lock().unwrap().push(request.viewer);
+        Ok(FeedResult {
+            cursor: None,
+            feed: vec![],
+        })
+    }
+}
@@ -1,14 +1,21 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct DidDocument {
-    #[serde(rename = "@context")]
+    #[serde(rename = "@context", default)]
     pub(crate) context: Vec<String>,
     pub(crate) id: String,
+    #[serde(default)]
     pub(crate) service: Vec<Service>,
+    #[serde(
+        rename = "verificationMethod",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub(crate) verification_method: Vec<VerificationMethod>,
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Service {
     pub(crate) id: String,
     #[serde(rename = "type")]
@@ -16,3 +23,20 @@ pub(crate) struct Service {
     #[serde(rename = "serviceEndpoint")]
     pub(crate) service_endpoint: String,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct VerificationMethod {
+    pub(crate) id: String,
+    #[serde(rename = "type")]
+    pub(crate) type_: String,
+    pub(crate) controller: String,
+    #[serde(rename = "publicKeyMultibase")]
+    pub(crate) public_key_multibase: Option<String>,
+}
+
+/// An XRPC error response body
+#[derive(Serialize)]
+pub(crate) struct XrpcError {
+    pub(crate) error: String,
+    pub(crate) message: String,
+}